@@ -0,0 +1,60 @@
+use std::io::Write;
+use std::path::Path;
+
+use clap::Args;
+use geolocate_core::asn::Asn;
+
+use crate::database::GeoDatabase;
+use crate::error::{CliError, Result};
+use crate::map::MaybeCountry;
+use crate::output::OutputArguments;
+
+/// The arguments for the 'asn' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// The autonomous system number to resolve, which may be prefixed with 'AS'.
+    #[arg(value_parser = geolocate_core::asn::parse_asn)]
+    pub number: Asn,
+    /// The file to source country-to-autonomous-system-number data from.
+    ///
+    /// This is read using the same format options as the IP source data, and may be an RIR delegated-stats file.
+    #[arg(short = 'f', long = "source-data")]
+    pub source: Box<Path>,
+    /// Also report the range of numbers that the given number was resolved from.
+    #[arg(short = 'e', long = "explain")]
+    pub explain: bool,
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// Runs the 'asn' command.
+///
+/// # Errors
+///
+/// This function will return an error if the command failed to execute, or [`AsnUnmapped`](<CliError::AsnUnmapped>)
+/// if the number was not mapped to any country. In the latter case, the number will have already been reported.
+pub fn run(Arguments { number, source, explain, output }: Arguments, database: &GeoDatabase) -> Result<()> {
+    let map = crate::map::parse_asn_map_file(source, None, database.options(), |c| database.resolve_country(c))?;
+
+    let Some((_, block, country)) = map.get_entry_from_address(number) else {
+        eprintln!("AS{number} is unmapped");
+
+        return Err(CliError::AsnUnmapped(number));
+    };
+
+    let mut out = output.open()?;
+
+    match country {
+        MaybeCountry::Present(country) => writeln!(out, "Country: {}", country.summary())?,
+        MaybeCountry::Missing(code) => writeln!(out, "Country: N/A ({code})")?,
+    }
+
+    if explain {
+        writeln!(out, "Block: AS{} .. AS{}", block.start(), block.end())?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use clap::Args;
+use geolocate_core::ip::{Address, IpAddrBlockMap};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::database::GeoDatabase;
+use crate::error::Result;
+use crate::filter::Filter;
+use crate::map::MaybeCountry;
+use crate::{Ipv4CountryMap, Ipv6CountryMap};
+
+/// The arguments for the 'browse' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// The initial search, matching a country's name, alpha-2 code, or numeric code.
+    ///
+    /// The search may be prefixed with 'name:', 'code:', or 'num:' to specify how it is matched.
+    pub search: Option<Box<str>>,
+}
+
+/// Runs the 'browse' command.
+///
+/// # Errors
+///
+/// This function will return an error if the command failed to execute.
+pub fn run(Arguments { search }: Arguments, database: &GeoDatabase) -> Result<()> {
+    let ipv4_map = database.ipv4_map()?;
+    let ipv6_map = database.ipv6_map()?;
+
+    let mut browser = Browser::new(ipv4_map, ipv6_map, search.as_deref().unwrap_or_default());
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+
+    ratatui::restore();
+
+    Ok(result?)
+}
+
+/// The state of the interactive browser.
+struct Browser<'d> {
+    /// The parsed IPv4 map.
+    ipv4_map: &'d Ipv4CountryMap,
+    /// The parsed IPv6 map.
+    ipv6_map: &'d Ipv6CountryMap,
+    /// Every country within either map, alongside its IPv4 and IPv6 block counts.
+    countries: Box<[(MaybeCountry, usize, usize)]>,
+    /// The indices of the countries that match the current search.
+    visible: Vec<usize>,
+    /// The current search text.
+    search: String,
+    /// Whether the search text is currently being edited.
+    searching: bool,
+    /// The selection state of the country list.
+    list_state: ListState,
+    /// The indices of the selected country's entries within the IPv4 map.
+    ipv4_indices: Box<[usize]>,
+    /// The indices of the selected country's entries within the IPv6 map.
+    ipv6_indices: Box<[usize]>,
+    /// The current page of the detail pane.
+    page: usize,
+    /// The number of entries displayed per page of the detail pane.
+    page_size: usize,
+}
+
+impl<'d> Browser<'d> {
+    /// Creates a new [`Browser`] over the given maps, applying the given initial search.
+    fn new(ipv4_map: &'d Ipv4CountryMap, ipv6_map: &'d Ipv6CountryMap, search: &str) -> Self {
+        let mut counts = HashMap::<MaybeCountry, (usize, usize)>::new();
+
+        for (_, country) in ipv4_map.iter() {
+            counts.entry(country.clone()).or_default().0 += 1;
+        }
+        for (_, country) in ipv6_map.iter() {
+            counts.entry(country.clone()).or_default().1 += 1;
+        }
+
+        let mut countries: Box<[_]> = counts.into_iter().map(|(c, (v4, v6))| (c, v4, v6)).collect();
+
+        countries.sort_unstable_by_key(|(c, ..)| c.code());
+
+        let mut browser = Self {
+            ipv4_map,
+            ipv6_map,
+            countries,
+            visible: Vec::new(),
+            search: search.to_string(),
+            searching: false,
+            list_state: ListState::default(),
+            ipv4_indices: Box::default(),
+            ipv6_indices: Box::default(),
+            page: 0,
+            page_size: 1,
+        };
+
+        browser.refresh();
+        browser
+    }
+
+    /// Runs the browser's event loop until it is closed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the terminal could not be drawn to or read from.
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else { continue };
+
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.searching {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                    KeyCode::Backspace => {
+                        self.search.pop();
+                        self.refresh();
+                    }
+                    KeyCode::Char(c) => {
+                        self.search.push(c);
+                        self.refresh();
+                    }
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => self.searching = true,
+                KeyCode::Up | KeyCode::Char('k') => {
+                    self.select(self.list_state.selected().map(|i| i.saturating_sub(1)));
+                }
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let last = self.visible.len().checked_sub(1);
+
+                    self.select(last.map(|last| self.list_state.selected().map_or(0, |i| i + 1).min(last)));
+                }
+                KeyCode::PageUp => self.page = self.page.saturating_sub(1),
+                KeyCode::PageDown => self.page = (self.page + 1).min(self.page_count() - 1),
+                _ => {}
+            }
+        }
+    }
+
+    /// Recomputes the visible countries from the current search, selecting the first match.
+    fn refresh(&mut self) {
+        let search = self.search.trim();
+        // An invalid search, such as an incomplete 'code:' prefix, matches nothing.
+        let filter = (!search.is_empty()).then(|| search.parse::<Filter>().ok());
+
+        self.visible = (0 .. self.countries.len())
+            .filter(|&i| {
+                filter
+                    .as_ref()
+                    .is_none_or(|f| f.as_ref().is_some_and(|f| f.test_maybe(&self.countries[i].0) == Some(true)))
+            })
+            .collect();
+
+        self.select((!self.visible.is_empty()).then_some(0));
+    }
+
+    /// Selects the visible country at the given index, collecting the indices of its map entries.
+    fn select(&mut self, index: Option<usize>) {
+        let country = index.map(|i| &self.countries[self.visible[i]].0);
+
+        self.ipv4_indices = country.map_or_else(Box::default, |c| self::indices_of(self.ipv4_map, c));
+        self.ipv6_indices = country.map_or_else(Box::default, |c| self::indices_of(self.ipv6_map, c));
+        self.list_state.select(index);
+        self.page = 0;
+    }
+
+    /// Returns the number of pages within the detail pane.
+    const fn page_count(&self) -> usize {
+        let total = self.ipv4_indices.len() + self.ipv6_indices.len();
+
+        if total == 0 { 1 } else { total.div_ceil(self.page_size) }
+    }
+
+    /// Returns the lines of the detail pane's current page.
+    fn detail_lines(&self) -> Vec<Line<'_>> {
+        let total = self.ipv4_indices.len() + self.ipv6_indices.len();
+
+        (self.page * self.page_size .. total)
+            .take(self.page_size)
+            .filter_map(|i| {
+                i.checked_sub(self.ipv4_indices.len()).map_or_else(
+                    || self::entry_line(self.ipv4_map, self.ipv4_indices[i]),
+                    |i| self::entry_line(self.ipv6_map, self.ipv6_indices[i]),
+                )
+            })
+            .collect()
+    }
+
+    /// Draws the browser into the given frame.
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search_area, body_area, help_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body_area);
+
+        let search_style = if self.searching { Style::new().add_modifier(Modifier::BOLD) } else { Style::new() };
+        let search = Paragraph::new(self.search.as_str()).style(search_style).block(Block::bordered().title("Search"));
+
+        frame.render_widget(search, search_area);
+
+        let items = self.visible.iter().map(|&i| {
+            let (country, ipv4_blocks, ipv6_blocks) = &self.countries[i];
+
+            ListItem::new(format!("{country} ({ipv4_blocks} IPv4, {ipv6_blocks} IPv6)"))
+        });
+        let list = List::new(items)
+            .block(Block::bordered().title("Countries"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(list, list_area, &mut self.list_state);
+
+        self.page_size = usize::from(detail_area.height.saturating_sub(2)).max(1);
+        self.page = self.page.min(self.page_count() - 1);
+
+        let title = format!("Blocks (page {}/{})", self.page + 1, self.page_count());
+        let detail = Paragraph::new(self.detail_lines()).block(Block::bordered().title(title));
+
+        frame.render_widget(detail, detail_area);
+        frame.render_widget(Paragraph::new("/ search  ↑↓ select  PgUp/PgDn scroll  q quit"), help_area);
+    }
+}
+
+/// Returns the indices of every entry within the given map that is assigned to the given country.
+fn indices_of<A: Address>(map: &IpAddrBlockMap<A, MaybeCountry>, country: &MaybeCountry) -> Box<[usize]> {
+    map.iter().enumerate().filter(|(_, (_, c))| *c == country).map(|(i, _)| i).collect()
+}
+
+/// Returns a line describing the entry at the given index within the given map.
+fn entry_line<A: Address + Display>(map: &IpAddrBlockMap<A, MaybeCountry>, index: usize) -> Option<Line<'static>> {
+    map.get_index(index).map(|(block, _)| Line::from(format!("{} .. {}", block.start(), block.end())))
+}
@@ -0,0 +1,81 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use clap::Args;
+use geolocate_core::ip::{Address, IpAddrBlockMap};
+use geolocate_core::prelude::CountryCode;
+
+use crate::database::GeoDatabase;
+use crate::error::Result;
+use crate::map::MaybeCountry;
+use crate::output::OutputArguments;
+
+/// The arguments for the 'check' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// Runs the 'check' command, reporting every country code within the IP source data that has no country data, and
+/// every country that has no blocks within the IP source data.
+///
+/// # Errors
+///
+/// This function will return an error if the command failed to execute.
+pub fn run(Arguments { output }: Arguments, database: &GeoDatabase) -> Result<()> {
+    let mut missing = BTreeMap::<CountryCode, (usize, usize)>::new();
+    let mut present = BTreeSet::new();
+
+    self::visit_codes(database.ipv4_map()?, &mut present, |code| missing.entry(code).or_default().0 += 1);
+    self::visit_codes(database.ipv6_map()?, &mut present, |code| missing.entry(code).or_default().1 += 1);
+
+    let mut unused: Box<[_]> = database.countries().filter(|c| !present.contains(&c.code)).collect();
+
+    unused.sort_unstable_by(|a, b| a.cmp_by_code(b));
+
+    let mut out = output.open()?;
+
+    if missing.is_empty() {
+        writeln!(out, "Every country code within the IP source data has country data.")?;
+    } else {
+        writeln!(out, "Country codes without country data ({}):", missing.len())?;
+
+        for (code, (ipv4_blocks, ipv6_blocks)) in missing {
+            writeln!(out, "    {} (IPv4: {ipv4_blocks}, IPv6: {ipv6_blocks})", code.display_with("unassigned"))?;
+        }
+    }
+
+    writeln!(out)?;
+
+    if unused.is_empty() {
+        writeln!(out, "Every country has blocks within the IP source data.")?;
+    } else {
+        writeln!(out, "Countries without blocks ({}):", unused.len())?;
+
+        for country in unused {
+            writeln!(out, "    {}", country.summary())?;
+        }
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Records the code of every country within the given map, calling `missing` for each block whose country is missing.
+fn visit_codes<A, F>(map: &IpAddrBlockMap<A, MaybeCountry>, present: &mut BTreeSet<CountryCode>, mut missing: F)
+where
+    A: Address,
+    F: FnMut(CountryCode),
+{
+    for country in map.values() {
+        match country {
+            MaybeCountry::Present(country) => {
+                present.insert(country.code);
+            }
+            MaybeCountry::Missing(code) => missing(*code),
+        }
+    }
+}
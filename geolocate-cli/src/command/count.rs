@@ -1,29 +1,96 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::io::Write;
 use std::num::NonZeroUsize;
-use std::path::Path;
+use std::rc::Rc;
 
-use anyhow::Result;
-use clap::Args;
+use clap::{Args, ValueEnum};
 use geolocate_core::ip::{Address, IpAddrBlock};
-use geolocate_core::prelude::*;
 
+use crate::database::GeoDatabase;
+use crate::error::Result;
 use crate::filter::Filter;
-use crate::map::MaybeCountry;
+use crate::map::{MaybeCountry, SortOrder};
+use crate::output::OutputArguments;
 
 /// The arguments for the 'count' command.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
 pub struct Arguments {
-    /// Only display the country with this name, alpha-2 code, or numeric code.
-    pub country: Option<Filter<'static>>,
-    /// Only display the specified number of countries. Does nothing when searching for a specific country.
+    /// Only display the countries with these names, alpha-2 codes, or numeric codes.
+    ///
+    /// Each filter may be prefixed with 'name:', 'code:', or 'num:' to specify how it is matched.
+    pub country: Vec<Filter<'static>>,
+    /// Only display the countries whose alpha-2 codes start with this prefix. May be specified multiple times.
+    #[arg(long = "code-prefix")]
+    pub code_prefix: Vec<Box<str>>,
+    /// Require a country to match every given country filter, rather than any of them.
+    #[arg(long = "match-all")]
+    pub match_all: bool,
+    /// Only display the specified number of countries. Does nothing when searching for specific countries.
     #[arg(short = 'c', long = "country-limit")]
     pub limit: Option<NonZeroUsize>,
+    /// Only display the specified number of countries with the most blocks, in descending order.
+    #[arg(short = 't', long = "top", conflicts_with_all = ["limit", "sort"])]
+    pub top: Option<NonZeroUsize>,
     /// Display IPv4 address blocks.
     #[arg(short = '4', long = "ipv4", required_if_eq("display_ipv6", "false"))]
     pub display_ipv4: bool,
     /// Display IPv6 address blocks.
     #[arg(short = '6', long = "ipv6", required_if_eq("display_ipv4", "false"))]
     pub display_ipv6: bool,
+    /// Only display blocks whose country code could not be resolved, grouped by their code.
+    #[arg(short = 'u', long = "unmapped", conflicts_with_all = ["country", "code_prefix"])]
+    pub unmapped: bool,
+    /// Only display countries with at least this many displayed blocks.
+    #[arg(long = "min-blocks")]
+    pub min_blocks: Option<usize>,
+    /// Only display countries with at most this many displayed blocks.
+    #[arg(long = "max-blocks")]
+    pub max_blocks: Option<usize>,
+    /// Discard all blocks not assigned to one of these countries before counting. May be specified multiple times.
+    #[arg(short = 'o', long = "only")]
+    pub only: Vec<Filter<'static>>,
+    /// Discard all blocks assigned to this country before counting. May be specified multiple times.
+    #[arg(short = 'x', long = "exclude")]
+    pub exclude: Vec<Filter<'static>>,
+    /// The number of decimal places to display percentages with.
+    #[arg(long = "precision", default_value = "1", value_parser = crate::number::parse_precision)]
+    pub precision: u8,
+    /// Do not display each count's percentage of the total number of blocks.
+    #[arg(long = "no-percent")]
+    pub no_percent: bool,
+    /// The format to display counts in.
+    #[arg(short = 'f', long = "format", value_enum, default_value = "text")]
+    pub format: Format,
+    /// Do not pad each count and percentage so that they line up across every displayed country.
+    #[arg(long = "no-align")]
+    pub no_align: bool,
+    /// Display a single total of each country's displayed IPv4 and IPv6 blocks, sorted in descending order.
+    #[arg(long = "combined", conflicts_with = "sort")]
+    pub combined: bool,
+    /// Display the number of addresses covered by the displayed IP versions' blocks, rather than any countries.
+    #[arg(long = "coverage", conflicts_with_all = [
+        "country", "code_prefix", "limit", "top", "unmapped", "combined", "min_blocks", "max_blocks"
+    ])]
+    pub coverage: bool,
+    /// The order to display countries in.
+    #[arg(long = "sort", value_enum, default_value = "code")]
+    pub sort: SortOrder,
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// The formats that counts may be displayed in.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Each country's counts listed beneath its name.
+    #[default]
+    Text,
+    /// A table with a row for each country and a column for each count, drawn with borders.
+    #[cfg(feature = "table")]
+    Table,
 }
 
 /// Runs the 'count' command.
@@ -31,33 +98,86 @@ pub struct Arguments {
 /// # Errors
 ///
 /// This function will return an error if the command failed to execute.
-pub fn run<'c>(
-    Arguments { country, limit, display_ipv4, display_ipv6 }: Arguments,
-    ipv4_source: &Path,
-    ipv6_source: &Path,
-    resolve: impl Fn(CountryCode) -> Option<Country> + Copy,
-    country_iter: impl Iterator<Item = &'c Country>,
+pub fn run(
+    Arguments {
+        mut country,
+        code_prefix,
+        match_all,
+        limit,
+        top,
+        display_ipv4,
+        display_ipv6,
+        unmapped,
+        min_blocks,
+        max_blocks,
+        only,
+        exclude,
+        precision,
+        no_percent,
+        format,
+        no_align,
+        combined,
+        coverage,
+        sort,
+        output,
+    }: Arguments,
+    database: &GeoDatabase,
 ) -> Result<()> {
-    let ipv4_map = crate::map::parse_ipv4_map_file(ipv4_source, None, resolve)?;
-    let ipv6_map = crate::map::parse_ipv6_map_file(ipv6_source, None, resolve)?;
+    let (ipv4_map, ipv6_map) = (database.ipv4_map()?, database.ipv6_map()?);
+    let ipv4_entries = || crate::filter::filter_entries(ipv4_map, &only, &exclude);
+    let ipv6_entries = || crate::filter::filter_entries(ipv6_map, &only, &exclude);
+
+    if coverage {
+        let mut out = output.open()?;
+
+        if display_ipv4 {
+            writeln!(out, "IPv4: {}", self::coverage_display(ipv4_entries().map(|(b, _)| *b).collect(), precision))?;
+        }
+        if display_ipv6 {
+            writeln!(out, "IPv6: {}", self::coverage_display(ipv6_entries().map(|(b, _)| *b).collect(), precision))?;
+        }
 
-    let mut countries: Box<[_]> = if let Some(filter) = country {
-        let country = crate::filter::find_country(&filter, country_iter)?;
-        let ipv4_blocks = display_ipv4.then(|| self::count_blocks(&filter, ipv4_map.iter()));
-        let ipv6_blocks = display_ipv6.then(|| self::count_blocks(&filter, ipv6_map.iter()));
+        out.flush()?;
 
-        Box::new([(MaybeCountry::Present(country), ipv4_blocks.unwrap_or_default(), ipv6_blocks.unwrap_or_default())])
+        return Ok(());
+    }
+
+    country.extend(code_prefix.into_iter().map(Filter::CodePrefix));
+
+    let countries: Box<[_]> = if let Some(filter) = Filter::combine(country, match_all) {
+        let country_list: Box<[_]> = database.countries().collect();
+
+        filter
+            .alternatives()
+            .iter()
+            .map(|filter| crate::filter::find_countries(filter, country_list.iter().copied()))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flat_map(<[_]>::into_vec)
+            .map(|country| {
+                let filter = Filter::Code(country.code);
+                let ipv4_blocks = display_ipv4.then(|| self::count_blocks(&filter, ipv4_entries()));
+                let ipv6_blocks = display_ipv6.then(|| self::count_blocks(&filter, ipv6_entries()));
+
+                (
+                    MaybeCountry::Present(Rc::new(country)),
+                    ipv4_blocks.unwrap_or_default(),
+                    ipv6_blocks.unwrap_or_default(),
+                )
+            })
+            .collect()
     } else {
         let mut countries = HashMap::<MaybeCountry, (usize, usize)>::new();
+        let included = |country: &MaybeCountry| !unmapped || matches!(country, MaybeCountry::Missing(_));
 
         if display_ipv4 {
-            for (_, country) in ipv4_map.iter() {
+            for (_, country) in ipv4_entries().filter(|(_, c)| included(c)) {
                 countries.entry(country.clone()).or_default().0 += 1;
             }
         }
 
         if display_ipv6 {
-            for (_, country) in ipv6_map.iter() {
+            for (_, country) in ipv6_entries().filter(|(_, c)| included(c)) {
                 countries.entry(country.clone()).or_default().1 += 1;
             }
         }
@@ -65,29 +185,142 @@ pub fn run<'c>(
         countries.into_iter().map(|(c, (v4, v6))| (c, v4, v6)).collect()
     };
 
-    countries.sort_unstable_by_key(|(c, ..)| match c {
-        MaybeCountry::Present(country) => country.code,
-        MaybeCountry::Missing(code) => *code,
-    });
+    // Thresholds apply to the total number of displayed blocks, which matches the total shown by '--combined'.
+    let within_thresholds =
+        |blocks: usize| min_blocks.is_none_or(|n| blocks >= n) && max_blocks.is_none_or(|n| blocks <= n);
+    let mut countries: Box<[_]> =
+        countries.into_vec().into_iter().filter(|(_, v4, v6)| within_thresholds(v4 + v6)).collect();
 
-    let limit = limit.map_or(countries.len(), NonZeroUsize::get);
+    countries.sort_unstable_by(|(a, ..), (b, ..)| a.cmp_by(b, sort));
 
-    for (country, ipv4_blocks, ipv6_blocks) in countries.iter_mut().take(limit) {
-        println!("{country}");
+    if top.is_some() || combined {
+        // The sort is stable, so countries with the same number of blocks remain in the requested order.
+        countries.sort_by_key(|(_, ipv4_blocks, ipv6_blocks)| Reverse(ipv4_blocks + ipv6_blocks));
+    }
 
-        if display_ipv4 {
-            println!("IPv4: {ipv4_blocks}");
-        }
-        if display_ipv6 {
-            println!("IPv6: {ipv6_blocks}");
+    let limit = top.or(limit).map_or(countries.len(), NonZeroUsize::get);
+    // Totals are taken from every filtered entry so that percentages are unaffected by the country limit.
+    let ipv4_total = if display_ipv4 { ipv4_entries().count() } else { 0 };
+    let ipv6_total = if display_ipv6 { ipv6_entries().count() } else { 0 };
+    let rows: Box<[_]> = countries
+        .iter()
+        .take(limit)
+        .map(|(country, ipv4_blocks, ipv6_blocks)| {
+            let mut counts = Vec::with_capacity(2);
+
+            if combined {
+                counts.push(("Total", ipv4_blocks + ipv6_blocks, ipv4_total + ipv6_total));
+            } else {
+                if display_ipv4 {
+                    counts.push(("IPv4", *ipv4_blocks, ipv4_total));
+                }
+                if display_ipv6 {
+                    counts.push(("IPv6", *ipv6_blocks, ipv6_total));
+                }
+            }
+
+            (country, counts)
+        })
+        .collect();
+
+    let mut out = output.open()?;
+
+    match format {
+        Format::Text => self::write_counts(&mut out, &rows, (!no_percent).then_some(precision), !no_align)?,
+        #[cfg(feature = "table")]
+        Format::Table => self::write_count_table(&mut out, &rows, (!no_percent).then_some(precision))?,
+    }
+
+    Ok(out.flush()?)
+}
+
+/// Writes each given country's labelled block counts, and their percentages of their totals if a precision is given.
+///
+/// If `align` is set, every count and percentage is right-aligned to the widest one across all of the countries.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+#[allow(clippy::type_complexity)]
+fn write_counts(
+    out: &mut impl Write,
+    rows: &[(&MaybeCountry, Vec<(&str, usize, usize)>)],
+    precision: Option<u8>,
+    align: bool,
+) -> Result<()> {
+    let cells = |blocks: usize, total: usize| {
+        let percent = precision.map(|p| format!("({})", crate::number::percentage(blocks as u128, total as u128, p)));
+
+        (crate::number::grouped(blocks as u128), percent.unwrap_or_default())
+    };
+    let (count_width, percent_width) = if align {
+        rows.iter().flat_map(|(_, counts)| counts).fold((0, 0), |(count_width, percent_width), &(_, blocks, total)| {
+            let (count, percent) = cells(blocks, total);
+
+            (count_width.max(count.len()), percent_width.max(percent.len()))
+        })
+    } else {
+        (0, 0)
+    };
+
+    for (country, counts) in rows {
+        writeln!(out, "{country}")?;
+
+        for &(label, blocks, total) in counts {
+            let (count, percent) = cells(blocks, total);
+
+            if precision.is_some() {
+                writeln!(out, "{label}: {count:>count_width$} {percent:>percent_width$}")?;
+            } else {
+                writeln!(out, "{label}: {count:>count_width$}")?;
+            }
         }
 
-        println!();
+        writeln!(out)?;
     }
 
     Ok(())
 }
 
+/// Writes each given country's labelled block counts as a table, with a column for their percentages of their totals
+/// if a precision is given.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+#[cfg(feature = "table")]
+#[allow(clippy::type_complexity)]
+fn write_count_table(
+    out: &mut impl Write,
+    rows: &[(&MaybeCountry, Vec<(&str, usize, usize)>)],
+    precision: Option<u8>,
+) -> Result<()> {
+    let mut header = vec!["Country", "Code"];
+
+    // Every row has the same labels, so the first row's labels are used for the header.
+    for &(label, ..) in rows.first().into_iter().flat_map(|(_, counts)| counts) {
+        header.push(label);
+
+        if precision.is_some() {
+            header.push("%");
+        }
+    }
+
+    let numeric: Box<[_]> = (2 .. header.len()).collect();
+    let rows = rows.iter().map(|(country, counts)| {
+        let mut row = vec![country.to_string(), country.code().to_string()];
+
+        for &(_, blocks, total) in counts {
+            row.push(crate::number::grouped(blocks as u128));
+            row.extend(precision.map(|p| crate::number::percentage(blocks as u128, total as u128, p)));
+        }
+
+        row
+    });
+
+    Ok(crate::output::write_table(out, &header, &numeric, rows)?)
+}
+
 /// Counts the total number of blocks in the given filtered iterator.
 pub fn count_blocks<'i, 'f, A, I>(filter: &Filter<'_>, iter: I) -> usize
 where
@@ -97,3 +330,18 @@ where
 {
     iter.filter(|(_, c)| filter.test_maybe(c).unwrap_or(false)).count()
 }
+
+/// Formats the number of addresses covered by the given blocks as a portion of the entire address space.
+///
+/// Addresses within overlapping blocks are only counted once, so the portion never exceeds the entire address space.
+fn coverage_display<A: Address>(mut blocks: Vec<IpAddrBlock<A>>, precision: u8) -> String {
+    geolocate_core::ip::merge_blocks(&mut blocks);
+
+    let covered = blocks.iter().fold(0_u128, |total, block| total.saturating_add(block.address_count()));
+    // The entire IPv6 address space is not representable as an integer, so both sides are halved; the lost precision
+    // is far smaller than any displayable precision.
+    let shift = A::BITS.saturating_sub(u128::BITS - 1);
+    let percentage = crate::number::percentage(covered >> shift, 1_u128 << (A::BITS - shift), precision);
+
+    format!("{} of 2^{} addresses ({percentage})", crate::number::grouped(covered), A::BITS)
+}
@@ -0,0 +1,153 @@
+use std::fmt::Display;
+use std::io::Write;
+use std::path::Path;
+
+use clap::Args;
+use geolocate_core::ip::{Address, IpAddrBlockMap};
+
+use crate::database::GeoDatabase;
+use crate::error::Result;
+use crate::map::MaybeCountry;
+use crate::output::OutputArguments;
+
+/// The arguments for the 'diff' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// The older source data file.
+    pub old: Box<Path>,
+    /// The newer source data file.
+    pub new: Box<Path>,
+    /// Compare the files as IPv6 source data rather than IPv4 source data.
+    #[arg(short = '6', long = "ipv6")]
+    pub ipv6: bool,
+    /// Display every changed block, rather than only the number of changes.
+    #[arg(short = 'v', long = "verbose")]
+    pub verbose: bool,
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// Runs the 'diff' command.
+///
+/// # Errors
+///
+/// This function will return an error if the command failed to execute.
+pub fn run(Arguments { old, new, ipv6, verbose, output }: Arguments, database: &GeoDatabase) -> Result<()> {
+    let (options, resolve) = (database.options(), |code| database.resolve_country(code));
+    let mut out = output.open()?;
+
+    if ipv6 {
+        let old = crate::map::parse_ipv6_map_file(old, None, options, resolve)?;
+        let new = crate::map::parse_ipv6_map_file(new, None, options, resolve)?;
+
+        self::print_diff(&mut out, &old, &new, verbose)?;
+    } else {
+        let old = crate::map::parse_ipv4_map_file(old, None, options, resolve)?;
+        let new = crate::map::parse_ipv4_map_file(new, None, options, resolve)?;
+
+        self::print_diff(&mut out, &old, &new, verbose)?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Writes the blocks that were added, removed, or reassigned between the given maps.
+///
+/// Blocks are compared by the addresses that they cover, so a block that was split or merged is only reported for the
+/// ranges whose assignment actually changed. Overlapping blocks are flattened first, so each address is compared using
+/// its most specific block within each map.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn print_diff<A>(
+    out: &mut impl Write,
+    old: &IpAddrBlockMap<A, MaybeCountry>,
+    new: &IpAddrBlockMap<A, MaybeCountry>,
+    verbose: bool,
+) -> std::io::Result<()>
+where
+    A: Address + Display,
+{
+    let mut changes: IpAddrBlockMap<A, (Option<MaybeCountry>, Option<MaybeCountry>)> =
+        old.flatten().into_iter().map(|(b, c)| (b, (Some(c.clone()), None))).collect();
+    let new = new.flatten().into_iter().map(|(b, c)| (b, (None, Some(c.clone())))).collect();
+
+    changes.union_with(&new, |(old, _), (_, new)| (old.clone(), new.clone()));
+
+    let (mut added, mut removed, mut reassigned) = (0_usize, 0_usize, 0_usize);
+
+    for (block, change) in changes.iter() {
+        let (symbol, description) = match change {
+            (None, Some(new)) => {
+                added += 1;
+
+                ('+', new.code().to_string())
+            }
+            (Some(old), None) => {
+                removed += 1;
+
+                ('-', old.code().to_string())
+            }
+            (Some(old), Some(new)) if old.code() != new.code() => {
+                reassigned += 1;
+
+                ('~', format!("{} -> {}", old.code(), new.code()))
+            }
+            _ => continue,
+        };
+
+        if verbose {
+            writeln!(out, "{symbol} {} .. {} ({description})", block.start(), block.end())?;
+        }
+    }
+
+    if verbose {
+        writeln!(out)?;
+    }
+
+    writeln!(out, "Added: {added}")?;
+    writeln!(out, "Removed: {removed}")?;
+    writeln!(out, "Reassigned: {reassigned}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::ParseOptions;
+
+    #[test]
+    fn diff_compares_most_specific_blocks() {
+        let directory = std::env::temp_dir();
+        let old_path = directory.join(format!("geolocate-diff-old-{}.csv", std::process::id()));
+        let new_path = directory.join(format!("geolocate-diff-new-{}.csv", std::process::id()));
+
+        std::fs::write(&old_path, "0,255,AU\n10,20,DE\n").unwrap();
+        std::fs::write(&new_path, "0,255,AU\n10,20,FR\n100,300,US\n").unwrap();
+
+        let old = crate::map::parse_ipv4_map_file(&old_path, None, ParseOptions::default(), |_| None).unwrap();
+        let new = crate::map::parse_ipv4_map_file(&new_path, None, ParseOptions::default(), |_| None).unwrap();
+        let mut out = Vec::new();
+
+        super::print_diff(&mut out, &old, &new, true).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            [
+                "~ 0.0.0.10 .. 0.0.0.20 (DE -> FR)",
+                "~ 0.0.0.100 .. 0.0.0.255 (AU -> US)",
+                "+ 0.0.1.0 .. 0.0.1.44 (US)",
+                "",
+                "Added: 1",
+                "Removed: 0",
+                "Reassigned: 2\n",
+            ]
+            .join("\n")
+        );
+
+        std::fs::remove_file(old_path).unwrap();
+        std::fs::remove_file(new_path).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use clap::Args;
+use geolocate_core::ip::{Address, IpAddrBlock, IpAddrBlockMap};
+use geolocate_core::prelude::CountryCode;
+use memmap2::Mmap;
+
+use crate::database::GeoDatabase;
+use crate::error::Result;
+use crate::map::MaybeCountry;
+
+/// The bytes that every index file starts with, which also record the version of its format.
+const MAGIC: &[u8; 8] = b"GEOIDX01";
+/// The length in bytes of an index file's header, which contains its magic bytes and its IPv4 and IPv6 record counts.
+const HEADER_LENGTH: usize = MAGIC.len() + 2 * size_of::<u64>();
+/// The length in bytes of a record's country code, which is padded with zeroes.
+const CODE_LENGTH: usize = 4;
+
+/// The arguments for the 'index' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// The file to write the index to.
+    pub path: Box<Path>,
+}
+
+/// Runs the 'index' command, writing both IP source data files into a single index file.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be parsed, or if the index could not be written.
+pub fn run(Arguments { path }: Arguments, database: &GeoDatabase) -> Result<()> {
+    let ipv4_map = database.ipv4_map()?;
+    let ipv6_map = database.ipv6_map()?;
+
+    let file = File::create(&path).with_context(|| format!("unable to create file '{}'", path.to_string_lossy()))?;
+    let mut out = BufWriter::new(file);
+
+    self::write_index(&mut out, ipv4_map, ipv6_map)?;
+
+    Ok(out.flush()?)
+}
+
+/// Writes an index of the given maps.
+///
+/// The index starts with a header containing [`MAGIC`] and the number of IPv4 and IPv6 records as little-endian
+/// integers. This is followed by the IPv4 records and then the IPv6 records, each sorted by start address. Every
+/// record contains its block's start and end addresses as big-endian integers, followed by its country code.
+///
+/// Overlapping blocks are split into disjoint records, each assigned the country of the most specific block that
+/// contains it, so that the index resolves every address to the same country as its map.
+///
+/// # Errors
+///
+/// This function will return an error if the index could not be written.
+fn write_index(
+    out: &mut impl Write,
+    ipv4_map: &IpAddrBlockMap<Ipv4Addr, MaybeCountry>,
+    ipv6_map: &IpAddrBlockMap<Ipv6Addr, MaybeCountry>,
+) -> std::io::Result<()> {
+    let ipv4_records = ipv4_map.flatten();
+    let ipv6_records = ipv6_map.flatten();
+
+    out.write_all(MAGIC)?;
+    out.write_all(&(ipv4_records.len() as u64).to_le_bytes())?;
+    out.write_all(&(ipv6_records.len() as u64).to_le_bytes())?;
+
+    self::write_records(out, &ipv4_records)?;
+    self::write_records(out, &ipv6_records)
+}
+
+/// Writes a fixed-width record for each of the given disjoint blocks, which must be sorted by start address.
+///
+/// # Errors
+///
+/// This function will return an error if a record could not be written.
+fn write_records<A: Address>(out: &mut impl Write, records: &[(IpAddrBlock<A>, &MaybeCountry)]) -> std::io::Result<()> {
+    let width = (A::BITS / u8::BITS) as usize;
+
+    for (block, country) in records {
+        let mut code = [0; CODE_LENGTH];
+        let display = country.code().to_string();
+
+        code[.. display.len()].copy_from_slice(display.as_bytes());
+
+        out.write_all(&block.start().to_bits().to_be_bytes()[size_of::<u128>() - width ..])?;
+        out.write_all(&block.end().to_bits().to_be_bytes()[size_of::<u128>() - width ..])?;
+        out.write_all(&code)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the country code assigned to the given address within the given index file, without reading the entire
+/// file.
+///
+/// The file is memory-mapped, and its records are binary-searched in place.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be mapped, or if it is not a valid index.
+pub fn lookup(path: &Path, address: IpAddr) -> Result<Option<CountryCode>> {
+    let invalid = || anyhow!("'{}' is not a valid index file", path.to_string_lossy());
+    let file = File::open(path).with_context(|| format!("unable to open file '{}'", path.to_string_lossy()))?;
+    // SAFETY: The mapping is only ever read from, and the index is not expected to be modified while it is in use. A
+    // modified index may produce incorrect results, but every read is still bounds-checked.
+    let index = unsafe { Mmap::map(&file) }.with_context(|| format!("unable to map '{}'", path.to_string_lossy()))?;
+
+    let header = index.get(.. HEADER_LENGTH).filter(|h| h.starts_with(MAGIC)).ok_or_else(invalid)?;
+    let count = |offset: usize| {
+        let mut bytes = [0; size_of::<u64>()];
+
+        bytes.copy_from_slice(&header[offset .. offset + size_of::<u64>()]);
+
+        usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| invalid())
+    };
+    let (ipv4_count, ipv6_count) = (count(MAGIC.len())?, count(MAGIC.len() + size_of::<u64>())?);
+    let ipv4_length = ipv4_count.checked_mul(2 * 4 + CODE_LENGTH).ok_or_else(invalid)?;
+
+    let (records, width, key) = match address {
+        IpAddr::V4(ip) => (index.get(HEADER_LENGTH ..).and_then(|r| r.get(.. ipv4_length)), 4, ip.to_bits().into()),
+        IpAddr::V6(ip) => {
+            let length = ipv6_count.checked_mul(2 * 16 + CODE_LENGTH).ok_or_else(invalid)?;
+
+            (index.get(HEADER_LENGTH + ipv4_length ..).and_then(|r| r.get(.. length)), 16, ip.to_bits())
+        }
+    };
+    let records = records.ok_or_else(invalid)?;
+    let record_length = 2 * width + CODE_LENGTH;
+    let bits = |bytes: &[u8]| bytes.iter().fold(0_u128, |bits, byte| (bits << 8) | u128::from(*byte));
+
+    // Finds the last record that starts at or before the address, which is the only record that may contain it, since
+    // overlapping blocks are split into disjoint records when the index is written.
+    let (mut low, mut high) = (0, records.len() / record_length);
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+
+        if bits(&records[middle * record_length ..][.. width]) <= key { low = middle + 1 } else { high = middle }
+    }
+
+    let Some(record) = low.checked_sub(1).map(|i| &records[i * record_length ..][.. record_length]) else {
+        return Ok(None);
+    };
+
+    if bits(&record[width .. 2 * width]) < key {
+        return Ok(None);
+    }
+
+    let code = &record[2 * width ..];
+    let code = std::str::from_utf8(&code[.. code.iter().position(|b| *b == 0).unwrap_or(CODE_LENGTH)])
+        .map_err(|_| invalid())?;
+
+    Ok(Some(CountryCode::from_str(code).map_err(|_| invalid())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::str::FromStr;
+
+    use geolocate_core::ip::{Address, IpAddrBlock, IpAddrBlockMap};
+    use geolocate_core::prelude::CountryCode;
+
+    use crate::map::MaybeCountry;
+
+    /// Returns an unresolved country with the given code.
+    fn country(code: &str) -> MaybeCountry {
+        MaybeCountry::Missing(CountryCode::from_str(code).unwrap())
+    }
+
+    /// Returns the addresses on and around the boundaries of every block within the given map.
+    fn boundaries<A: Address, T>(map: &IpAddrBlockMap<A, T>) -> Vec<A> {
+        let max = A::from_bits(u128::MAX).to_bits();
+
+        map.blocks()
+            .flat_map(|b| {
+                let (start, end) = (b.start().to_bits(), b.end().to_bits());
+
+                [start.saturating_sub(1), start, start + (end - start) / 2, end, end.saturating_add(1).min(max)]
+            })
+            .map(A::from_bits)
+            .collect()
+    }
+
+    #[test]
+    fn lookup_matches_overlapping_map() {
+        let ipv4 = |start: [u8; 4], end: [u8; 4]| IpAddrBlock::new(Ipv4Addr::from(start), Ipv4Addr::from(end));
+        let ipv6 = |start: &str, end: &str| IpAddrBlock::new(start.parse::<Ipv6Addr>().unwrap(), end.parse().unwrap());
+
+        let ipv4_map: IpAddrBlockMap<_, _> = [
+            (ipv4([1, 0, 0, 0], [1, 0, 0, 255]), country("AU")),
+            (ipv4([1, 0, 0, 84], [1, 0, 0, 184]), country("DE")),
+            (ipv4([1, 0, 0, 100], [1, 0, 0, 120]), country("JP")),
+            (ipv4([1, 0, 1, 0], [1, 0, 1, 255]), country("CN")),
+            (ipv4([255, 255, 255, 0], [255, 255, 255, 255]), country("US")),
+            (ipv4([255, 255, 255, 128], [255, 255, 255, 255]), country("FR")),
+        ]
+        .into_iter()
+        .collect();
+        let ipv6_map: IpAddrBlockMap<_, _> = [
+            (ipv6("2001:db8::", "2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"), country("NL")),
+            (ipv6("2001:db8:1::", "2001:db8:1:ffff:ffff:ffff:ffff:ffff"), country("BE")),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(!ipv4_map.is_disjoint());
+
+        let path = std::env::temp_dir().join(format!("geolocate-index-{}.idx", std::process::id()));
+
+        super::write_index(&mut File::create(&path).unwrap(), &ipv4_map, &ipv6_map).unwrap();
+
+        for address in self::boundaries(&ipv4_map) {
+            let expected = ipv4_map.get_from_address(address).map(MaybeCountry::code);
+
+            assert_eq!(super::lookup(&path, IpAddr::V4(address)).unwrap(), expected, "{address}");
+        }
+        for address in self::boundaries(&ipv6_map) {
+            let expected = ipv6_map.get_from_address(address).map(MaybeCountry::code);
+
+            assert_eq!(super::lookup(&path, IpAddr::V6(address)).unwrap(), expected, "{address}");
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
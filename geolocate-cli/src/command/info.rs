@@ -0,0 +1,89 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use clap::Args;
+use geolocate_core::ip::{Address, IpAddrBlockMap};
+
+use crate::database::GeoDatabase;
+use crate::error::Result;
+use crate::map::MaybeCountry;
+use crate::output::OutputArguments;
+
+/// The arguments for the 'info' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// Runs the 'info' command, reporting where the country data and IP source data were read from, alongside each file's
+/// size, modification time, and the number of entries and countries parsed from it.
+///
+/// The given country source is the file that the country data was read from, or [`None`] if the embedded country data
+/// was used.
+///
+/// # Errors
+///
+/// This function will return an error if the command failed to execute.
+pub fn run(Arguments { output }: Arguments, country_source: Option<&Path>, database: &GeoDatabase) -> Result<()> {
+    let ipv4_map = database.ipv4_map()?;
+    let ipv6_map = database.ipv6_map()?;
+
+    let mut out = output.open()?;
+
+    match country_source {
+        Some(path) if path == Path::new("-") => writeln!(out, "Country data: standard input")?,
+        Some(path) => self::write_file(&mut out, "Country data", path)?,
+        None => writeln!(out, "Country data: embedded")?,
+    }
+
+    writeln!(out, "    Countries: {}", database.countries().count())?;
+    writeln!(out)?;
+
+    self::write_file(&mut out, "IPv4 source data", database.ipv4_source())?;
+    self::write_map(&mut out, ipv4_map)?;
+    writeln!(out)?;
+
+    self::write_file(&mut out, "IPv6 source data", database.ipv6_source())?;
+    self::write_map(&mut out, ipv6_map)?;
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Writes the given file's path, size, and modification time beneath the given label.
+///
+/// # Errors
+///
+/// This function will return an error if the file's metadata could not be read, or if the output could not be written.
+fn write_file(out: &mut impl Write, label: &str, path: &Path) -> Result<()> {
+    let metadata = std::fs::metadata(path)?;
+
+    writeln!(out, "{label}: {}", path.to_string_lossy())?;
+    writeln!(out, "    Size: {} bytes", metadata.len())?;
+
+    // Some platforms do not record modification times, in which case the line is omitted.
+    if let Ok(seconds) = metadata.modified().map(|time| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())) {
+        writeln!(out, "    Modified: {}", crate::output::timestamp_display(seconds))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the number of entries within the given map, and the number of distinct countries that they are assigned to.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn write_map<A: Address>(out: &mut impl Write, map: &IpAddrBlockMap<A, MaybeCountry>) -> Result<()> {
+    let countries: BTreeSet<_> = map.values().map(MaybeCountry::code).collect();
+
+    writeln!(out, "    Entries: {}", map.len())?;
+    writeln!(out, "    Countries: {}", countries.len())?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use clap::Args;
+
+use crate::error::Result;
+
+/// The arguments for the 'init' command.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+pub struct Arguments {
+    /// The file to write the country data to.
+    #[arg(default_value = crate::DEFAULT_COUNTRY_SOURCE)]
+    pub path: Box<Path>,
+    /// Overwrite the file if it already exists.
+    #[arg(short = 'f', long = "force")]
+    pub force: bool,
+}
+
+/// Runs the 'init' command, writing the embedded country data to the given file.
+///
+/// # Errors
+///
+/// This function will return an error if the file already exists and `force` is not set, or if it could not be
+/// written.
+pub fn run(Arguments { path, force }: Arguments) -> Result<()> {
+    let display = path.to_string_lossy();
+
+    if !force && std::fs::exists(&path).with_context(|| format!("unable to access file '{display}'"))? {
+        return Err(anyhow!("the file '{display}' already exists; use '--force' to overwrite it").into());
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("unable to create directory '{}'", parent.to_string_lossy()))?;
+    }
+
+    std::fs::write(&path, crate::EMBEDDED_COUNTRIES).with_context(|| format!("unable to write file '{display}'"))?;
+
+    println!("Wrote country data to '{display}'");
+
+    Ok(())
+}
@@ -1,24 +1,44 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::io::Write;
 use std::num::NonZeroUsize;
 use std::path::Path;
+use std::rc::Rc;
 
-use anyhow::Result;
-use clap::Args;
+use anyhow::anyhow;
+use clap::{Args, ValueEnum};
 use geolocate_core::ip::{Address, IpAddrBlock};
 use geolocate_core::prelude::*;
+use serde::Serialize;
 
+use crate::database::GeoDatabase;
+use crate::error::Result;
 use crate::filter::Filter;
-use crate::map::MaybeCountry;
+use crate::map::{MaybeCountry, SortOrder};
+use crate::output::OutputArguments;
 
 /// The arguments for the 'list' command.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
 pub struct Arguments {
-    /// Only display the country with this name, alpha-2 code, or numeric code.
-    pub country: Option<Filter<'static>>,
+    /// Only display the countries with these names, alpha-2 codes, or numeric codes.
+    ///
+    /// Each filter may be prefixed with 'name:', 'code:', or 'num:' to specify how it is matched. A country's blocks
+    /// may be listed by its ISO 3166-1 numeric code using 'num:', such as 'num:276' for Germany.
+    pub country: Vec<Filter<'static>>,
+    /// Only display the countries whose alpha-2 codes start with this prefix. May be specified multiple times.
+    #[arg(long = "code-prefix")]
+    pub code_prefix: Vec<Box<str>>,
+    /// Require a country to match every given country filter, rather than any of them.
+    #[arg(long = "match-all")]
+    pub match_all: bool,
     /// Only display the specified number of countries.
     #[arg(short = 'c', long = "country-limit")]
     pub country_limit: Option<NonZeroUsize>,
+    /// Only display the specified number of countries with the most blocks, in descending order.
+    #[arg(short = 't', long = "top", conflicts_with_all = ["country_limit", "sort"])]
+    pub top: Option<NonZeroUsize>,
     /// Only display the specified number of addresses.
     #[arg(short = 'a', long = "address-limit")]
     pub address_limit: Option<NonZeroUsize>,
@@ -28,6 +48,132 @@ pub struct Arguments {
     /// Display IPv6 address blocks.
     #[arg(short = '6', long = "ipv6", required_if_eq("display_ipv4", "false"))]
     pub display_ipv6: bool,
+    /// Display each country's addresses as the minimal set of CIDR prefixes that cover its blocks.
+    #[arg(short = 'A', long = "aggregate")]
+    pub aggregate: bool,
+    /// Display each block as a CIDR prefix if it is a single aligned prefix, and as a range otherwise.
+    #[arg(long = "addresses-as-cidr", conflicts_with = "aggregate")]
+    pub cidr: bool,
+    /// The format to display blocks in.
+    #[arg(short = 'f', long = "format", value_enum, default_value = "text")]
+    pub format: Format,
+    /// The comma-separated columns to display for each block, in order, when using the 'csv' or 'table' format.
+    #[arg(long = "columns", value_enum, value_delimiter = ',', default_value = "name,code,version,block")]
+    pub columns: Vec<Column>,
+    /// Do not pad each block's start address so that its ranges line up across every displayed country.
+    #[arg(long = "no-align")]
+    pub no_align: bool,
+    /// Display each country's code and numeric code alongside its name.
+    #[arg(short = 'v', long = "verbose")]
+    pub verbose: bool,
+    /// Discard all blocks not assigned to one of these countries before listing. May be specified multiple times.
+    #[arg(short = 'o', long = "only")]
+    pub only: Vec<Filter<'static>>,
+    /// Discard all blocks assigned to this country before listing. May be specified multiple times.
+    #[arg(short = 'x', long = "exclude")]
+    pub exclude: Vec<Filter<'static>>,
+    /// The order to display countries in.
+    #[arg(long = "sort", value_enum, default_value = "code")]
+    pub sort: SortOrder,
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// The formats that blocks may be displayed in.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Blocks grouped beneath each country's name.
+    #[default]
+    Text,
+    /// One JSON object per block, holding the block's country code, IP version, and start and end addresses.
+    ///
+    /// Blocks are always displayed as ranges, so this ignores '--aggregate' and '--addresses-as-cidr'.
+    Jsonl,
+    /// CSV rows of each block's start address, end address, and country code, in the format of the IP source data.
+    ///
+    /// The rows are sorted by address and preceded by '#' comments recording when and from which files they were
+    /// generated, so the output may be used as IP source data. This requires exactly one of '--ipv4' or '--ipv6'.
+    Geoip,
+    /// CSV rows of the columns chosen by '--columns' for each block, preceded by a header row naming them.
+    Csv,
+    /// A table with a row for each block, listing the columns chosen by '--columns', drawn with borders.
+    #[cfg(feature = "table")]
+    Table,
+}
+
+/// The columns that may be displayed for each block by the 'csv' and 'table' formats.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// The alpha-2 code of the block's country.
+    Code,
+    /// The name of the block's country.
+    Name,
+    /// The numeric code of the block's country, if it has one.
+    Numeric,
+    /// The block's IP version.
+    Version,
+    /// The block's first address.
+    Start,
+    /// The block's last address.
+    End,
+    /// The block as a CIDR prefix, if it is a single aligned prefix.
+    Cidr,
+    /// The block as it is displayed by the 'text' format, respecting '--aggregate' and '--addresses-as-cidr'.
+    Block,
+}
+
+impl Column {
+    /// Returns the title of this column within a table's header row.
+    #[must_use]
+    pub const fn title(self) -> &'static str {
+        match self {
+            Self::Code => "Code",
+            Self::Name => "Country",
+            Self::Numeric => "Numeric",
+            Self::Version => "Version",
+            Self::Start => "Start",
+            Self::End => "End",
+            Self::Cidr => "CIDR",
+            Self::Block => "Block",
+        }
+    }
+
+    /// Returns the value of this column for the given block, which is assigned to the given country.
+    ///
+    /// If `cidr` is set, the [`Block`](<Column::Block>) column displays blocks that are single aligned prefixes as
+    /// those prefixes.
+    pub fn value<A>(self, country: &MaybeCountry, block: &IpAddrBlock<A>, cidr: bool) -> String
+    where
+        A: Address + Display,
+    {
+        match self {
+            Self::Code => country.code().to_string(),
+            Self::Name => country.to_string(),
+            Self::Numeric => country.country().and_then(|c| c.numeric).map(|n| n.to_string()).unwrap_or_default(),
+            Self::Version => if A::BITS == u32::BITS { "4" } else { "6" }.to_string(),
+            Self::Start => block.start().to_string(),
+            Self::End => block.end().to_string(),
+            Self::Cidr => block.as_prefix().map(|p| p.to_string()).unwrap_or_default(),
+            Self::Block => block
+                .as_prefix()
+                .filter(|_| cidr)
+                .map_or_else(|| format!("{} .. {}", block.start(), block.end()), |prefix| prefix.to_string()),
+        }
+    }
+}
+
+/// A single line of JSON Lines output.
+#[derive(Serialize)]
+struct JsonLine {
+    /// The code of the country that the block is assigned to.
+    country: CountryCode,
+    /// The block's IP version.
+    version: u8,
+    /// The block's first address.
+    start: String,
+    /// The block's last address.
+    end: String,
 }
 
 /// Runs the 'list' command.
@@ -35,92 +181,245 @@ pub struct Arguments {
 /// # Errors
 ///
 /// This function will return an error if the command failed to execute.
-pub fn run<'c>(
-    Arguments { country, country_limit, address_limit, display_ipv4, display_ipv6 }: Arguments,
-    ipv4_source: &Path,
-    ipv6_source: &Path,
-    resolve: impl Fn(CountryCode) -> Option<Country> + Copy,
-    country_iter: impl Iterator<Item = &'c Country>,
+pub fn run(
+    Arguments {
+        mut country,
+        code_prefix,
+        match_all,
+        country_limit,
+        top,
+        address_limit,
+        display_ipv4,
+        display_ipv6,
+        aggregate,
+        cidr,
+        format,
+        columns,
+        verbose,
+        no_align,
+        only,
+        exclude,
+        sort,
+        output,
+    }: Arguments,
+    database: &GeoDatabase,
 ) -> Result<()> {
-    let mut countries: Box<[_]> = if let Some(filter) = country {
-        let country = crate::filter::find_country(&filter, country_iter)?;
+    if format == Format::Geoip && display_ipv4 == display_ipv6 {
+        return Err(anyhow!("the 'geoip' format requires exactly one of '--ipv4' or '--ipv6'").into());
+    }
 
-        let ipv4_blocks = if display_ipv4 {
-            let ipv4_map = crate::map::parse_ipv4_map_file(ipv4_source, None, resolve)?;
+    country.extend(code_prefix.into_iter().map(Filter::CodePrefix));
 
-            Some(self::collect_blocks(Some(&filter), ipv4_map.iter()))
-        } else {
-            None
-        };
-        let ipv6_blocks = if display_ipv6 {
-            let ipv6_map = crate::map::parse_ipv6_map_file(ipv6_source, None, resolve)?;
+    let ipv4_map = if display_ipv4 { Some(database.ipv4_map()?) } else { None };
+    let ipv6_map = if display_ipv6 { Some(database.ipv6_map()?) } else { None };
+    let ipv4_entries = |map| crate::filter::filter_entries(map, &only, &exclude);
+    let ipv6_entries = |map| crate::filter::filter_entries(map, &only, &exclude);
 
-            Some(self::collect_blocks(Some(&filter), ipv6_map.iter()))
-        } else {
-            None
-        };
+    let mut countries: Box<[_]> = if let Some(filter) = Filter::combine(country, match_all) {
+        let country_list: Box<[_]> = database.countries().collect();
 
-        Box::new([(MaybeCountry::Present(country), ipv4_blocks.unwrap_or_default(), ipv6_blocks.unwrap_or_default())])
+        filter
+            .alternatives()
+            .iter()
+            .map(|filter| crate::filter::find_countries(filter, country_list.iter().copied()))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flat_map(<[_]>::into_vec)
+            .map(|country| {
+                let filter = Filter::Code(country.code);
+                let ipv4_blocks = ipv4_map.map(|m| self::collect_blocks(Some(&filter), ipv4_entries(m)));
+                let ipv6_blocks = ipv6_map.map(|m| self::collect_blocks(Some(&filter), ipv6_entries(m)));
+
+                (
+                    MaybeCountry::Present(Rc::new(country)),
+                    ipv4_blocks.unwrap_or_default(),
+                    ipv6_blocks.unwrap_or_default(),
+                )
+            })
+            .collect()
     } else {
-        let mut countries: HashMap<_, (Vec<_>, Vec<_>)> = HashMap::new();
+        self::group_blocks(
+            ipv4_map.map(ipv4_entries).into_iter().flatten(),
+            ipv6_map.map(ipv6_entries).into_iter().flatten(),
+        )
+    };
 
-        if display_ipv4 {
-            let ipv4_map = crate::map::parse_ipv4_map_file(ipv4_source, None, resolve)?;
+    countries.sort_unstable_by(|(a, ..), (b, ..)| a.cmp_by(b, sort));
 
-            for (address_block, country) in ipv4_map.iter() {
-                countries.entry(country.clone()).or_default().0.push(*address_block);
-            }
-        }
+    if top.is_some() {
+        // The sort is stable, so countries with the same number of blocks remain in the requested order.
+        countries.sort_by_key(|(_, ipv4_blocks, ipv6_blocks)| Reverse(ipv4_blocks.len() + ipv6_blocks.len()));
+    }
 
-        if display_ipv6 {
-            let ipv6_map = crate::map::parse_ipv6_map_file(ipv6_source, None, resolve)?;
+    let country_limit = top.or(country_limit).map_or(countries.len(), NonZeroUsize::get);
+    let address_limit = address_limit.map_or(usize::MAX, NonZeroUsize::get);
 
-            for (address_block, country) in ipv6_map.iter() {
-                countries.entry(country.clone()).or_default().1.push(*address_block);
-            }
-        }
+    let mut out = output.open()?;
 
-        countries.into_iter().map(|(c, (v4, v6))| (c, v4.into_boxed_slice(), v6.into_boxed_slice())).collect()
-    };
+    if format == Format::Geoip {
+        let countries = countries.iter().take(country_limit);
+
+        return if display_ipv4 {
+            self::write_geoip(&mut out, database.ipv4_source(), countries.map(|(c, v4, _)| (c, &**v4)), address_limit)
+        } else {
+            self::write_geoip(&mut out, database.ipv6_source(), countries.map(|(c, _, v6)| (c, &**v6)), address_limit)
+        };
+    }
 
-    countries.sort_unstable_by_key(|(c, ..)| match c {
-        MaybeCountry::Present(country) => country.code,
-        MaybeCountry::Missing(code) => *code,
-    });
+    let column_rows = |countries| self::column_rows(countries, &columns, address_limit, aggregate, cidr);
 
-    let country_limit = country_limit.map_or(countries.len(), NonZeroUsize::get);
+    if format == Format::Csv {
+        return self::write_csv(&mut out, &columns, column_rows(countries.iter_mut().take(country_limit)));
+    }
+    #[cfg(feature = "table")]
+    if format == Format::Table {
+        let header: Box<[_]> = columns.iter().map(|c| c.title()).collect();
+        let numeric: Box<[_]> = (0 .. columns.len()).filter(|i| columns[*i] == Column::Numeric).collect();
+
+        return Ok(crate::output::write_table(
+            &mut out,
+            &header,
+            &numeric,
+            column_rows(countries.iter_mut().take(country_limit)),
+        )?);
+    }
+
+    // Aggregated blocks are displayed as prefixes, so there are no ranges to align.
+    let aligned = countries.iter().take(country_limit).filter(|_| !no_align && !aggregate);
+    let (ipv4_width, ipv6_width) = self::start_widths(aligned, address_limit);
 
     for (country, ipv4_blocks, ipv6_blocks) in countries.iter_mut().take(country_limit) {
         if ipv4_blocks.is_empty() && ipv6_blocks.is_empty() {
             continue;
         }
 
-        println!("{country}");
+        if format == Format::Jsonl {
+            self::write_json_lines(&mut out, country.code(), ipv4_blocks, ipv6_blocks, address_limit)?;
 
-        ipv6_blocks.sort_unstable();
+            continue;
+        }
+
+        match country {
+            MaybeCountry::Present(country) if verbose => writeln!(out, "{}", country.summary())?,
+            country => writeln!(out, "{country}")?,
+        }
 
         if display_ipv4 {
             ipv4_blocks.sort_unstable();
 
-            let limit = address_limit.map_or(ipv4_blocks.len(), NonZeroUsize::get);
-
-            println!("\nIPv4:\n    {}", self::blocks_display(limit, ipv4_blocks.iter()));
+            writeln!(
+                out,
+                "\nIPv4:\n    {}",
+                self::blocks_display(address_limit, aggregate, cidr, ipv4_width, ipv4_blocks)
+            )?;
         }
 
         if display_ipv6 {
             ipv6_blocks.sort_unstable();
 
-            let limit = address_limit.map_or(ipv6_blocks.len(), NonZeroUsize::get);
+            writeln!(
+                out,
+                "\nIPv6:\n    {}",
+                self::blocks_display(address_limit, aggregate, cidr, ipv6_width, ipv6_blocks)
+            )?;
+        }
+
+        writeln!(out)?;
+    }
+
+    Ok(out.flush()?)
+}
 
-            println!("\nIPv6:\n    {}", self::blocks_display(limit, ipv6_blocks.iter()));
+/// Writes the given countries' blocks as IP source data, sorted by address and preceded by provenance comments.
+///
+/// IPv4 addresses are written as integers, matching the format of Tor's `geoip` file.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn write_geoip<'c, A, I>(out: &mut impl Write, source: &Path, countries: I, limit: usize) -> Result<()>
+where
+    A: Address + Display + 'c,
+    I: Iterator<Item = (&'c MaybeCountry, &'c [IpAddrBlock<A>])>,
+{
+    let mut rows: Vec<_> =
+        countries.flat_map(|(c, blocks)| blocks.iter().take(limit).map(|b| (*b, c.code()))).collect();
+
+    rows.sort_unstable();
+
+    crate::output::write_provenance(out, &[source])?;
+
+    for (block, code) in rows {
+        if A::BITS == u32::BITS {
+            writeln!(out, "{},{},{code}", block.start().to_bits(), block.end().to_bits())?;
+        } else {
+            writeln!(out, "{},{},{code}", block.start(), block.end())?;
         }
+    }
+
+    Ok(out.flush()?)
+}
+
+/// Writes a JSON Lines entry for each of the given country's IPv4 and IPv6 blocks, sorting them first.
+///
+/// # Errors
+///
+/// This function will return an error if a line could not be written.
+fn write_json_lines(
+    out: &mut impl Write,
+    code: CountryCode,
+    ipv4_blocks: &mut [Ipv4AddrBlock],
+    ipv6_blocks: &mut [Ipv6AddrBlock],
+    limit: usize,
+) -> Result<()> {
+    ipv4_blocks.sort_unstable();
+    ipv6_blocks.sort_unstable();
 
-        println!();
+    for block in ipv4_blocks.iter().take(limit) {
+        self::write_json_line(out, code, 4, block)?;
+    }
+    for block in ipv6_blocks.iter().take(limit) {
+        self::write_json_line(out, code, 6, block)?;
     }
 
     Ok(())
 }
 
+/// Writes a single JSON Lines entry for the given block.
+///
+/// # Errors
+///
+/// This function will return an error if the line could not be written.
+fn write_json_line<A>(out: &mut impl Write, country: CountryCode, version: u8, block: &IpAddrBlock<A>) -> Result<()>
+where
+    A: Address + Display,
+{
+    let line = JsonLine { country, version, start: block.start().to_string(), end: block.end().to_string() };
+
+    serde_json::to_writer(&mut *out, &line).map_err(std::io::Error::from)?;
+
+    Ok(writeln!(out)?)
+}
+
+/// Groups every given IPv4 and IPv6 block by the country that it is assigned to.
+#[allow(clippy::type_complexity)]
+fn group_blocks<'m>(
+    ipv4_entries: impl Iterator<Item = (&'m Ipv4AddrBlock, &'m MaybeCountry)>,
+    ipv6_entries: impl Iterator<Item = (&'m Ipv6AddrBlock, &'m MaybeCountry)>,
+) -> Box<[(MaybeCountry, Box<[Ipv4AddrBlock]>, Box<[Ipv6AddrBlock]>)]> {
+    let mut countries: HashMap<_, (Vec<_>, Vec<_>)> = HashMap::new();
+
+    for (address_block, country) in ipv4_entries {
+        countries.entry(country.clone()).or_default().0.push(*address_block);
+    }
+
+    for (address_block, country) in ipv6_entries {
+        countries.entry(country.clone()).or_default().1.push(*address_block);
+    }
+
+    countries.into_iter().map(|(c, (v4, v6))| (c, v4.into_boxed_slice(), v6.into_boxed_slice())).collect()
+}
+
 /// Collects IP address blocks from the given iterator into a list.
 fn collect_blocks<'i, 'f, A, I>(filter: Option<&Filter<'f>>, iter: I) -> Box<[IpAddrBlock<A>]>
 where
@@ -137,15 +436,116 @@ where
     iter.map(|(b, _)| *b).collect()
 }
 
-/// Returns a display implementation for the given address block list.
-fn blocks_display<'b, A, I>(limit: usize, blocks: I) -> impl Display
+/// Returns the lengths of the longest IPv4 and IPv6 start addresses within the given countries' displayed blocks.
+fn start_widths<'c, I>(countries: I, limit: usize) -> (usize, usize)
+where
+    I: Iterator<Item = &'c (MaybeCountry, Box<[Ipv4AddrBlock]>, Box<[Ipv6AddrBlock]>)>,
+{
+    fn width<A: Address + Display>(blocks: &[IpAddrBlock<A>], limit: usize) -> usize {
+        blocks.iter().take(limit).map(|b| b.start().to_string().len()).max().unwrap_or_default()
+    }
+
+    countries.fold((0, 0), |(ipv4_width, ipv6_width), (_, ipv4_blocks, ipv6_blocks)| {
+        (ipv4_width.max(width(ipv4_blocks, limit)), ipv6_width.max(width(ipv6_blocks, limit)))
+    })
+}
+
+/// Returns a row of the given columns for each of the given countries' blocks, sorting them first.
+///
+/// If `aggregate` is set, each country's blocks are merged into their minimal covering CIDR prefixes, each of which is
+/// given its own row.
+fn column_rows<'c, I>(countries: I, columns: &[Column], limit: usize, aggregate: bool, cidr: bool) -> Vec<Vec<String>>
+where
+    I: Iterator<Item = &'c mut (MaybeCountry, Box<[Ipv4AddrBlock]>, Box<[Ipv6AddrBlock]>)>,
+{
+    let mut rows = Vec::new();
+
+    for (country, ipv4_blocks, ipv6_blocks) in countries {
+        ipv4_blocks.sort_unstable();
+        ipv6_blocks.sort_unstable();
+
+        for block in self::row_blocks(limit, aggregate, ipv4_blocks) {
+            rows.push(columns.iter().map(|c| c.value(country, &block, cidr || aggregate)).collect());
+        }
+        for block in self::row_blocks(limit, aggregate, ipv6_blocks) {
+            rows.push(columns.iter().map(|c| c.value(country, &block, cidr || aggregate)).collect());
+        }
+    }
+
+    rows
+}
+
+/// Returns the blocks to display as rows for the given sorted address block list.
+///
+/// If `aggregate` is set, the blocks are merged and split into their minimal covering CIDR prefixes.
+fn row_blocks<A: Address>(limit: usize, aggregate: bool, blocks: &[IpAddrBlock<A>]) -> Vec<IpAddrBlock<A>> {
+    if !aggregate {
+        return blocks.iter().take(limit).copied().collect();
+    }
+
+    let mut blocks = blocks.to_vec();
+
+    geolocate_core::ip::merge_blocks(&mut blocks);
+
+    blocks.into_iter().flat_map(|b| b.prefixes()).map(|p| p.block()).take(limit).collect()
+}
+
+/// Writes the given rows as CSV records, beneath a header row naming the given columns.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn write_csv(out: &mut impl Write, columns: &[Column], rows: Vec<Vec<String>>) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    let header = columns.iter().filter_map(ValueEnum::to_possible_value).map(|v| v.get_name().to_string());
+
+    writer.write_record(header).map_err(std::io::Error::from)?;
+
+    for row in rows {
+        writer.write_record(row).map_err(std::io::Error::from)?;
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Returns a display implementation for the given sorted address block list.
+///
+/// See [`block_lines`] for how each block is displayed.
+fn blocks_display<A>(limit: usize, aggregate: bool, cidr: bool, width: usize, blocks: &[IpAddrBlock<A>]) -> impl Display
 where
-    A: Address + Display + 'b,
-    I: Iterator<Item = &'b IpAddrBlock<A>>,
+    A: Address + Display,
 {
-    blocks
-        .take(limit)
-        .map(|b| format!("{} .. {}", b.start(), b.end()))
-        .intersperse("\n    ".to_string())
-        .collect::<Box<str>>()
+    self::block_lines(limit, aggregate, cidr, width, blocks).intersperse("\n    ".to_string()).collect::<Box<str>>()
+}
+
+/// Returns an iterator over the display of each block within the given sorted address block list.
+///
+/// If `aggregate` is set, the blocks are merged and displayed as their minimal covering CIDR prefixes. Otherwise, if
+/// `cidr` is set, each block that is a single aligned prefix is displayed as that prefix. Ranges have their start
+/// addresses padded to the given width.
+fn block_lines<A>(
+    limit: usize,
+    aggregate: bool,
+    cidr: bool,
+    width: usize,
+    blocks: &[IpAddrBlock<A>],
+) -> impl Iterator<Item = String> + '_
+where
+    A: Address + Display,
+{
+    let lines: Box<dyn Iterator<Item = String>> = if aggregate {
+        let mut blocks = blocks.to_vec();
+
+        geolocate_core::ip::merge_blocks(&mut blocks);
+
+        Box::new(blocks.into_iter().flat_map(|b| b.prefixes()).map(|p| p.to_string()))
+    } else {
+        Box::new(blocks.iter().map(move |b| {
+            b.as_prefix()
+                .filter(|_| cidr)
+                .map_or_else(|| format!("{:<width$} .. {}", b.start(), b.end()), |prefix| prefix.to_string())
+        }))
+    };
+
+    lines.take(limit)
 }
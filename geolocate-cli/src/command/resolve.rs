@@ -1,17 +1,51 @@
-use std::net::IpAddr;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Write;
+use std::net::{IpAddr, ToSocketAddrs};
 use std::path::Path;
+use std::str::FromStr;
 
-use anyhow::{bail, Result};
-use clap::Args;
-use geolocate_core::prelude::{Country, CountryCode};
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, ValueEnum};
+use csv::Position;
+use geolocate_core::ip::prefix::PrefixResolution;
+use geolocate_core::ip::{Address, IpAddrBlock, IpAddrBlockMap};
+use geolocate_core::prelude::{CountryCode, NumericCode};
+use serde::Serialize;
 
+use crate::database::GeoDatabase;
+use crate::error::{CliError, Result};
+use crate::filter::Filter;
+use crate::ip::Reservation;
 use crate::map::MaybeCountry;
+use crate::output::{Output, OutputArguments};
 
 /// The arguments for the 'count' command.
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Args)]
+#[group(id = "batch", multiple = false, args = ["stdin", "hostname"])]
 pub struct Arguments {
-    /// The IP address to resolve.
-    pub address: IpAddr,
+    /// The IP address to resolve, or a CIDR subnet such as '1.0.0.0/24'.
+    ///
+    /// A subnet resolves to a single country only if all of its addresses are assigned to that country. Otherwise, it
+    /// is reported as partially mapped or as ambiguous between each country that it contains.
+    #[arg(required_unless_present_any = ["stdin", "hostname"])]
+    pub address: Option<Query>,
+    /// Resolve a list of newline-separated IP addresses read from the standard input.
+    #[arg(short = 's', long = "stdin", conflicts_with = "address")]
+    pub stdin: bool,
+    /// Look up the given hostname's addresses using the system's DNS resolver, and resolve each of them.
+    ///
+    /// Every returned address is resolved using the source data of its IP version, so a hostname with both A and AAAA
+    /// records is resolved using both. The lookup is subject to the system resolver's timeout.
+    #[arg(long = "resolve-dns", value_name = "HOSTNAME", conflicts_with_all = ["address", "stdin"])]
+    pub hostname: Option<Box<str>>,
+    /// Write each address that could not be resolved to this file, one per line, rather than to the output.
+    ///
+    /// The number of resolved and unmapped addresses is reported on the standard error once every address has been
+    /// resolved, unless '--quiet' is given.
+    #[arg(long = "unmapped-out", value_name = "FILE", requires = "batch")]
+    pub unmapped_out: Option<Box<Path>>,
     /// Output the country's name. This is enabled by default if no arguments are provided.
     #[arg(short = 'n', long = "name")]
     pub name: bool,
@@ -21,62 +55,941 @@ pub struct Arguments {
     /// Output the country's numeric code.
     #[arg(short = 'N', long = "numeric")]
     pub numeric: bool,
+    /// Output the country's IANA timezones, if the country data contains them.
+    #[arg(short = 't', long = "timezone")]
+    pub timezone: bool,
+    /// Do not resolve IPv4-mapped or IPv4-compatible IPv6 addresses using the IPv4 source data.
+    #[arg(long = "no-unmap")]
+    pub no_unmap: bool,
+    /// Also report how many blocks of the other IP version are assigned to the resolved country.
+    #[arg(long = "all-sources", conflicts_with_all = ["stdin", "hostname"])]
+    pub all_sources: bool,
+    /// Also report the total number of blocks and addresses assigned to the resolved country.
+    #[arg(long = "stats", conflicts_with_all = ["stdin", "hostname"])]
+    pub stats: bool,
+    /// Do not output anything, reporting the result only through the exit code.
+    ///
+    /// The exit code is 0 if every address was resolved, 2 if any address was unmapped, and 1 on error, including
+    /// invalid arguments.
+    #[arg(short = 'q', long = "quiet", conflicts_with_all = ["all_sources", "stats"])]
+    pub quiet: bool,
+    /// Also report the matched block, its index within the map, and the country code given by the source data.
+    #[arg(short = 'e', long = "explain", conflicts_with_all = ["stdin", "hostname", "quiet"])]
+    pub explain: bool,
+    /// Also output the line number and raw text of the source data record that the address was resolved from.
+    #[arg(long = "show-source", conflicts_with_all = ["stdin", "hostname", "quiet"])]
+    pub show_source: bool,
+    /// Resolve the address using both the IPv4 and IPv6 source data, reporting the matches within each.
+    ///
+    /// IPv4 addresses are looked up within the IPv6 source data as IPv4-mapped addresses, and IPv6 addresses are only
+    /// looked up within the IPv4 source data if they embed an IPv4 address. This reads both source data files for
+    /// every lookup, and a file that fails to parse is reported without preventing the other from being searched.
+    #[arg(long = "both", conflicts_with_all = ["stdin", "hostname", "all_sources", "stats", "explain", "show_source"])]
+    pub both: bool,
+    /// Report the number and proportion of the given subnet's addresses assigned to each country, rather than
+    /// resolving a single address.
+    ///
+    /// A plain address is treated as a subnet containing only that address.
+    #[arg(long = "summary", conflicts_with_all = [
+        "stdin", "hostname", "all_sources", "stats", "quiet", "explain", "show_source", "both",
+    ])]
+    pub summary: bool,
+    /// Resolve the address using an index file created by the 'index' command, rather than the source data.
+    ///
+    /// The index is memory-mapped and searched in place, so it does not need to be parsed.
+    #[cfg(feature = "index")]
+    #[arg(long = "index", value_name = "FILE", conflicts_with_all = [
+        "stdin", "hostname", "all_sources", "stats", "explain", "show_source", "both", "summary",
+    ])]
+    pub index: Option<Box<Path>>,
+    /// Report addresses within special-purpose ranges, such as private or loopback addresses, as reserved rather than
+    /// resolving them.
+    ///
+    /// These addresses are never assigned to a country, so they are reported as 'Reserved (private)' or similar rather
+    /// than as unmapped. They are still treated as unmapped by the exit code.
+    #[arg(long = "classify", conflicts_with_all = ["both", "summary"])]
+    pub classify: bool,
+    /// The format to output each address' country in.
+    #[arg(short = 'f', long = "format", value_enum, default_value = "text", conflicts_with_all = [
+        "unmapped_out", "all_sources", "stats", "quiet", "explain", "show_source", "both", "summary", "classify",
+    ])]
+    #[cfg_attr(feature = "index", arg(conflicts_with = "index"))]
+    pub format: Format,
+    /// The text to output in place of country codes that could not be parsed from the source data.
+    #[arg(long = "unassigned", default_value = "??")]
+    pub unassigned: Box<str>,
+    /// Where to write the command's output.
+    #[command(flatten)]
+    pub output: OutputArguments,
+}
+
+/// An address or CIDR subnet given to the 'resolve' command.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Query {
+    /// A single address.
+    Address(IpAddr),
+    /// A subnet, given by its network address and prefix length.
+    Subnet(IpAddr, u8),
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((network, length)) = value.split_once('/') else {
+            return Ok(Self::Address(IpAddr::from_str(value)?));
+        };
+
+        let network = IpAddr::from_str(network)?;
+        let length = length.parse().with_context(|| format!("invalid prefix length '{length}'"))?;
+
+        // The subnet is validated here, so that it may be converted into a block infallibly later.
+        match network {
+            IpAddr::V4(ip) => _ = IpAddrBlock::from_prefix(ip, length)?,
+            IpAddr::V6(ip) => _ = IpAddrBlock::from_prefix(ip, length)?,
+        }
+
+        Ok(Self::Subnet(network, length))
+    }
+}
+
+/// The formats that the 'resolve' command may output countries in.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The requested fields of each country, one per line.
+    #[default]
+    Text,
+    /// A GeoJSON feature collection with a feature for each address, ignoring the requested fields.
+    ///
+    /// Every feature has a null geometry, and its properties contain the address and its country's alpha-2 code,
+    /// name, and numeric code.
+    Geojson,
+}
+
+/// A GeoJSON feature collection.
+#[derive(Serialize)]
+struct FeatureCollection<'c> {
+    /// The GeoJSON object type, which is always `FeatureCollection`.
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The collection's features.
+    features: Vec<Feature<'c>>,
+}
+
+/// A GeoJSON feature describing a single resolved address.
+#[derive(Serialize)]
+struct Feature<'c> {
+    /// The GeoJSON object type, which is always `Feature`.
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// The feature's geometry, which is always `null` since countries have no coordinates.
+    geometry: Option<()>,
+    /// The feature's properties.
+    properties: FeatureProperties<'c>,
+}
+
+/// The properties of a resolved address' GeoJSON feature.
+#[derive(Serialize)]
+struct FeatureProperties<'c> {
+    /// The resolved address.
+    address: IpAddr,
+    /// The alpha-2 code of the address' country, if it is known.
+    code: Option<CountryCode>,
+    /// The name of the address' country, if it is known.
+    name: Option<&'c str>,
+    /// The numeric code of the address' country, if it is known.
+    numeric: Option<NumericCode>,
+}
+
+/// The fields of a country to output.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct Fields<'s> {
+    /// Whether to output the country's name.
+    name: bool,
+    /// Whether to output the country's alpha-2 code.
+    code: bool,
+    /// Whether to output the country's numeric code.
+    numeric: bool,
+    /// Whether to output the country's timezones.
+    timezone: bool,
+    /// The text to output in place of unassigned country codes.
+    unassigned: &'s str,
 }
 
 /// Runs the 'resolve' command.
 ///
 /// # Errors
 ///
-/// This function will return an error if the command failed to execute.
+/// This function will return an error if the command failed to execute, or an error for which
+/// [`CliError::is_unmapped`] is true if any address was unmapped or, when classifying addresses, reserved. In the
+/// latter case, each such address will have already been reported unless `quiet` is set.
 pub fn run(
-    Arguments { address, mut name, code, numeric }: Arguments,
-    ipv4_source: &Path,
-    ipv6_source: &Path,
-    resolve: impl Fn(CountryCode) -> Option<Country> + Copy,
+    Arguments {
+        address,
+        stdin: _,
+        hostname,
+        unmapped_out,
+        mut name,
+        code,
+        numeric,
+        timezone,
+        no_unmap,
+        all_sources,
+        stats,
+        quiet,
+        explain,
+        show_source,
+        both,
+        summary,
+        #[cfg(feature = "index")]
+        index,
+        classify,
+        format,
+        unassigned,
+        output,
+    }: Arguments,
+    database: &GeoDatabase,
+) -> Result<()> {
+    // The name is output by default if no other fields are requested.
+    name |= !code && !numeric && !timezone;
+
+    let fields = Fields { name, code, numeric, timezone, unassigned: &unassigned };
+    let mut out = output.open()?;
+
+    let address = match address {
+        Some(query) if summary => {
+            return self::run_summary(&mut out, query, database);
+        }
+        Some(Query::Address(address)) => Some(address),
+        Some(Query::Subnet(..)) if explain || stats || show_source || all_sources || both || classify => {
+            return Err(anyhow!("a subnet may only be resolved on its own or using '--summary'").into());
+        }
+        #[cfg(feature = "index")]
+        Some(Query::Subnet(..)) if index.is_some() => {
+            return Err(anyhow!("a subnet may not be resolved using '--index'").into());
+        }
+        Some(Query::Subnet(network, length)) => {
+            return self::run_prefix(&mut out, quiet, network, length, database, fields);
+        }
+        None => None,
+    };
+
+    if format == Format::Geojson {
+        let addresses = address.map_or_else(
+            || hostname.as_deref().map_or_else(self::read_addresses, self::lookup_addresses),
+            |a| Ok(Box::from([a])),
+        )?;
+        let addresses: Box<[_]> =
+            addresses.iter().map(|&a| if no_unmap { a } else { self::unmap_address(a) }).collect();
+
+        return self::run_geojson(&mut out, &addresses, database);
+    }
+
+    let Some(address) = address else {
+        let addresses = hostname.as_deref().map_or_else(self::read_addresses, self::lookup_addresses)?;
+        let addresses: Box<[_]> =
+            addresses.iter().map(|&a| if no_unmap { a } else { self::unmap_address(a) }).collect();
+
+        let out: &mut dyn Write = if quiet { &mut std::io::sink() } else { &mut out };
+        let unmapped_out = unmapped_out.map(|path| OutputArguments { output: Some(path) }.open()).transpose()?;
+
+        return self::run_batch(out, unmapped_out, quiet, classify, &addresses, database, fields);
+    };
+
+    if both {
+        let out: &mut dyn Write = if quiet { &mut std::io::sink() } else { &mut out };
+
+        return self::run_both(out, address, database, fields);
+    }
+
+    let address = if no_unmap { address } else { self::unmap_address(address) };
+
+    if let Some(reservation) = crate::ip::reservation(address).filter(|_| classify) {
+        return self::report_reservation(&mut out, quiet, address, reservation);
+    }
+
+    #[cfg(feature = "index")]
+    if let Some(index) = index.as_deref() {
+        return self::run_index(&mut out, quiet, index, address, database, fields);
+    }
+
+    let source = if address.is_ipv4() { database.ipv4_source() } else { database.ipv6_source() };
+    // The full map is only needed to explain the match, report statistics, or describe the ranges surrounding a miss.
+    // Strict parsing must also check that every record's country code resolves, which streaming does not.
+    let streamed = if explain || stats || database.options().strict {
+        None
+    } else {
+        match crate::map::resolve_streaming(source, database.options(), |c| database.resolve_country(c), address)? {
+            None if quiet => return Err(CliError::AddressUnmapped(address)),
+            country => country,
+        }
+    };
+
+    let (country, matched) = match (&streamed, address) {
+        (Some((country, _)), _) => {
+            if !quiet {
+                self::print_country(&mut out, country, fields)?;
+            }
+
+            (country.clone(), None)
+        }
+        (None, IpAddr::V4(ip)) => {
+            let ipv4_map = database.ipv4_map()?;
+
+            self::resolve_within(&mut out, ipv4_map, ip, quiet, explain, stats, fields)?
+        }
+        (None, IpAddr::V6(ip)) => {
+            let ipv6_map = database.ipv6_map()?;
+
+            self::resolve_within(&mut out, ipv6_map, ip, quiet, explain, stats, fields)?
+        }
+    };
+
+    if show_source {
+        let position = streamed.map(|(_, position)| position);
+
+        self::print_source(&mut out, source, database, address, position, matched)?;
+    }
+
+    if all_sources {
+        self::print_other_source(&mut out, address, &country, database)?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Writes the line number and raw text of the source data record that the given address was resolved from.
+///
+/// If the full map was parsed rather than streamed, the record's position is not yet known, and is found by searching
+/// for the record that the matched block was parsed from.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be read, or if the output could not be written.
+fn print_source(
+    out: &mut impl Write,
+    source: &Path,
+    database: &GeoDatabase,
+    address: IpAddr,
+    position: Option<Position>,
+    matched: Option<(IpAddr, IpAddr)>,
 ) -> Result<()> {
-    if !name && !code && !numeric {
-        name = true;
+    let position = match (position, matched) {
+        (Some(position), _) => Some(position),
+        (None, Some(matched)) => {
+            let resolve = |code| database.resolve_country(code);
+
+            crate::map::find_source_record(source, database.options(), resolve, address, matched)?
+        }
+        (None, None) => None,
+    };
+
+    if let Some(position) = position {
+        let (line, record) = crate::map::read_source_record(source, database.options(), &position)?;
+
+        writeln!(out, "Source line {line}: {record}")?;
     }
 
-    let Some(country) = (match address {
+    Ok(())
+}
+
+/// Writes that the given address is within the given special-purpose range.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written, or
+/// [`AddressReserved`](<CliError::AddressReserved>) otherwise.
+fn report_reservation(out: &mut impl Write, quiet: bool, address: IpAddr, reservation: Reservation) -> Result<()> {
+    if !quiet {
+        writeln!(out, "Reserved ({reservation})")?;
+        out.flush()?;
+    }
+
+    Err(CliError::AddressReserved(address, reservation))
+}
+
+/// Resolves the given address within the given fully-parsed map, writing its country and any requested details.
+///
+/// Alongside the country, this returns the first and last addresses of the block that the address was matched to.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written, or
+/// [`AddressUnmapped`](<CliError::AddressUnmapped>) if the address was unmapped. In the latter case, the ranges
+/// surrounding the address will have already been reported unless `quiet` is set.
+fn resolve_within<A>(
+    out: &mut impl Write,
+    map: &IpAddrBlockMap<A, MaybeCountry>,
+    address: A,
+    quiet: bool,
+    explain: bool,
+    stats: bool,
+    fields: Fields<'_>,
+) -> Result<(MaybeCountry, Option<(IpAddr, IpAddr)>)>
+where
+    A: Address + Display + Into<IpAddr>,
+{
+    let Some((index, block, country)) = map.get_entry_from_address(address) else {
+        if !quiet {
+            eprintln!("{}", self::unmapped_message(map, address));
+        }
+
+        return Err(CliError::AddressUnmapped(address.into()));
+    };
+
+    if !quiet {
+        self::print_country(out, country, fields)?;
+    }
+    if explain {
+        self::print_explanation(out, index, block, country, fields.unassigned)?;
+    }
+    if stats {
+        self::print_stats(out, map, country)?;
+    }
+
+    Ok((country.clone(), Some((block.start().into(), block.end().into()))))
+}
+
+/// Resolves the given address using the given index file, writing its country.
+///
+/// # Errors
+///
+/// This function will return an error if the index could not be read, or
+/// [`AddressUnmapped`](<CliError::AddressUnmapped>) if the address was unmapped.
+#[cfg(feature = "index")]
+fn run_index(
+    out: &mut impl Write,
+    quiet: bool,
+    index: &Path,
+    address: IpAddr,
+    database: &GeoDatabase,
+    fields: Fields,
+) -> Result<()> {
+    let Some(code) = crate::command::index::lookup(index, address)? else {
+        return Err(CliError::AddressUnmapped(address));
+    };
+    let country = database.resolve_country(code).map_or(MaybeCountry::Missing(code), MaybeCountry::Present);
+
+    if !quiet {
+        self::print_country(out, &country, fields)?;
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Resolves the CIDR subnet with the given network address and prefix length, writing its country if it has only one,
+/// or each of its countries if it is ambiguous.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be parsed, if the output could not be written, or
+/// [`AddressUnmapped`](<CliError::AddressUnmapped>) if no address within the subnet is mapped.
+#[allow(clippy::too_many_arguments)]
+fn run_prefix(
+    out: &mut impl Write,
+    quiet: bool,
+    network: IpAddr,
+    length: u8,
+    database: &GeoDatabase,
+    fields: Fields<'_>,
+) -> Result<()> {
+    let write = |out: &mut dyn Write, resolution: PrefixResolution<'_, MaybeCountry>| -> Result<()> {
+        match resolution {
+            PrefixResolution::Unmapped => {
+                if !quiet {
+                    eprintln!("no address within the given subnet is mapped");
+                }
+
+                return Err(CliError::AddressUnmapped(network));
+            }
+            _ if quiet => {}
+            PrefixResolution::Single(country) => self::print_country(out, country, fields)?,
+            PrefixResolution::Partial(country) => {
+                self::print_country(out, country, fields)?;
+                writeln!(out, "Partially unmapped")?;
+            }
+            PrefixResolution::Ambiguous(countries) => {
+                let countries = countries.iter().map(|c| format!("{c} ({})", c.code().display_with(fields.unassigned)));
+
+                writeln!(out, "Ambiguous: {}", countries.collect::<Box<[_]>>().join(", "))?;
+            }
+        }
+
+        Ok(())
+    };
+
+    match network {
+        IpAddr::V4(ip) => {
+            let map = database.ipv4_map()?;
+
+            write(out, map.get_from_prefix(ip, length).map_err(|error| anyhow!("{error}"))?)?;
+        }
+        IpAddr::V6(ip) => {
+            let map = database.ipv6_map()?;
+
+            write(out, map.get_from_prefix(ip, length).map_err(|error| anyhow!("{error}"))?)?;
+        }
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Writes the number of blocks assigned to the given country within the source data of the other IP version.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be parsed, or if the output could not be written.
+fn print_other_source(
+    out: &mut impl Write,
+    address: IpAddr,
+    country: &MaybeCountry,
+    database: &GeoDatabase,
+) -> Result<()> {
+    let filter = Filter::Code(country.code());
+
+    match address {
+        IpAddr::V4(_) => {
+            let ipv6_map = database.ipv6_map()?;
+
+            writeln!(out, "IPv6 blocks: {}", crate::command::count::count_blocks(&filter, ipv6_map.iter()))?;
+        }
+        IpAddr::V6(_) => {
+            let ipv4_map = database.ipv4_map()?;
+
+            writeln!(out, "IPv4 blocks: {}", crate::command::count::count_blocks(&filter, ipv4_map.iter()))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the number and proportion of the given subnet's addresses that are assigned to each country.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be parsed, or if the output could not be written.
+fn run_summary(out: &mut impl Write, query: Query, database: &GeoDatabase) -> Result<()> {
+    let (network, length) = match query {
+        Query::Address(address) => (address, if address.is_ipv4() { 32 } else { 128 }),
+        Query::Subnet(network, length) => (network, length),
+    };
+
+    match network {
         IpAddr::V4(ip) => {
-            let ipv4_map = crate::map::parse_ipv4_map_file(ipv4_source, None, resolve)?;
+            let map = database.ipv4_map()?;
+            let block = IpAddrBlock::from_prefix(ip, length).map_err(|error| anyhow!("{error}"))?;
 
-            ipv4_map.get_from_address(ip).cloned()
+            self::print_summary(out, map, block)?;
         }
         IpAddr::V6(ip) => {
-            let ipv6_map = crate::map::parse_ipv6_map_file(ipv6_source, None, resolve)?;
+            let map = database.ipv6_map()?;
+            let block = IpAddrBlock::from_prefix(ip, length).map_err(|error| anyhow!("{error}"))?;
 
-            ipv6_map.get_from_address(ip).cloned()
+            self::print_summary(out, map, block)?;
         }
-    }) else {
-        bail!("the given ip address is unmapped");
+    }
+
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Writes the number and proportion of the given block's addresses that are assigned to each country, ordered by
+/// descending address count.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn print_summary<A: Address + Display>(
+    out: &mut impl Write,
+    map: &IpAddrBlockMap<A, MaybeCountry>,
+    block: IpAddrBlock<A>,
+) -> std::io::Result<()> {
+    let total = block.address_count();
+    let mut countries = HashMap::<&MaybeCountry, u128>::new();
+
+    for (b, country) in map.overlapping(block) {
+        let shared = b.intersection(&block).map_or(0, |b| b.address_count());
+
+        *countries.entry(country).or_default() += shared;
+    }
+
+    let mapped = countries.values().fold(0_u128, |sum, count| sum.saturating_add(*count));
+    let mut countries: Box<[_]> = countries.into_iter().collect();
+
+    countries.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.code().cmp(&b.code())));
+
+    let display =
+        |count: u128| format!("{} ({})", crate::number::grouped(count), crate::number::percentage(count, total, 1));
+
+    writeln!(out, "Subnet: {} .. {} ({} addresses)", block.start(), block.end(), crate::number::grouped(total))?;
+
+    for (country, count) in countries {
+        writeln!(out, "{country} ({}): {}", country.code(), display(count))?;
+    }
+
+    // Overlapping blocks may assign the same address more than once, so the unmapped count cannot be negative.
+    writeln!(out, "Unmapped: {}", display(total.saturating_sub(mapped)))
+}
+
+/// Resolves the given address within both source data files, writing the match found within each.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written, or
+/// [`AddressUnmapped`](<CliError::AddressUnmapped>) if neither file contains the address.
+fn run_both(out: &mut dyn Write, address: IpAddr, database: &GeoDatabase, fields: Fields<'_>) -> Result<()> {
+    let ipv4_address = match self::unmap_address(address) {
+        IpAddr::V4(ip) => Some(IpAddr::V4(ip)),
+        IpAddr::V6(_) => None,
     };
+    let ipv6_address = match address {
+        IpAddr::V4(ip) => IpAddr::V6(ip.to_ipv6_mapped()),
+        IpAddr::V6(_) => address,
+    };
+    let lookups =
+        [("IPv4", database.ipv4_source(), ipv4_address), ("IPv6", database.ipv6_source(), Some(ipv6_address))];
+    let mut any_mapped = false;
+
+    for (version, source, address) in lookups {
+        let Some(address) = address else { continue };
+
+        writeln!(out, "{version} source data ({address}):")?;
+
+        match crate::map::resolve_streaming(source, database.options(), |c| database.resolve_country(c), address) {
+            Ok(Some((country, _))) => {
+                any_mapped = true;
+
+                self::print_country(out, &country, fields)?;
+            }
+            Ok(None) => writeln!(out, "Unmapped")?,
+            Err(error) => {
+                eprintln!("{error}");
+                writeln!(out, "Unreadable")?;
+            }
+        }
+
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+
+    if any_mapped { Ok(()) } else { Err(CliError::AddressUnmapped(address)) }
+}
+
+/// Resolves each of the given addresses, writing their countries in order.
+///
+/// Each source map is only parsed if an address of its IP version is present.
+///
+/// If `unmapped_out` is given, unmapped addresses are written to it rather than to `out`, and the number of resolved
+/// and unmapped addresses is reported on the standard error unless `quiet` is set. If `classify` is set, addresses
+/// within special-purpose ranges are reported as reserved, and are never written to `unmapped_out`.
+///
+/// # Errors
+///
+/// This function will return an error if a source map could not be parsed or the output could not be written, or
+/// [`AddressesUnmapped`](<CliError::AddressesUnmapped>) if any address was unmapped or reserved.
+#[allow(clippy::too_many_arguments)]
+fn run_batch(
+    out: &mut dyn Write,
+    mut unmapped_out: Option<Output>,
+    quiet: bool,
+    classify: bool,
+    addresses: &[IpAddr],
+    database: &GeoDatabase,
+    fields: Fields<'_>,
+) -> Result<()> {
+    let countries = self::resolve_all(addresses, database)?;
+    let (mut unmapped, mut reserved) = (0_usize, 0_usize);
+
+    for (address, country) in addresses.iter().zip(countries) {
+        if let Some(reservation) = crate::ip::reservation(*address).filter(|_| classify) {
+            reserved += 1;
+
+            writeln!(out, "{address}\nReserved ({reservation})\n")?;
+
+            continue;
+        }
+
+        if country.is_none() {
+            unmapped += 1;
+
+            if let Some(unmapped_out) = &mut unmapped_out {
+                writeln!(unmapped_out, "{address}")?;
+
+                continue;
+            }
+        }
+
+        writeln!(out, "{address}")?;
+
+        match country {
+            Some(country) => self::print_country(out, &country, fields)?,
+            None => writeln!(out, "Unmapped")?,
+        }
+
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+
+    if let Some(mut unmapped_out) = unmapped_out {
+        unmapped_out.flush()?;
+
+        if !quiet {
+            let resolved = addresses.len() - unmapped - reserved;
+
+            eprint!("Resolved {resolved} of {} addresses; {unmapped} unmapped", addresses.len());
+
+            if classify {
+                eprint!(", {reserved} reserved");
+            }
+
+            eprintln!();
+        }
+    }
 
+    if unmapped + reserved > 0 { Err(CliError::AddressesUnmapped(unmapped + reserved)) } else { Ok(()) }
+}
+
+/// Resolves each of the given addresses to its country, in order.
+///
+/// Each source map is only parsed if an address of its IP version is present.
+///
+/// # Errors
+///
+/// This function will return an error if a source map could not be parsed.
+fn resolve_all(addresses: &[IpAddr], database: &GeoDatabase) -> Result<Box<[Option<MaybeCountry>]>> {
+    let ipv4_addresses: Box<[_]> =
+        addresses.iter().filter_map(|a| if let IpAddr::V4(ip) = a { Some(*ip) } else { None }).collect();
+    let ipv6_addresses: Box<[_]> =
+        addresses.iter().filter_map(|a| if let IpAddr::V6(ip) = a { Some(*ip) } else { None }).collect();
+
+    let ipv4_map = if ipv4_addresses.is_empty() { None } else { Some(database.ipv4_map()?) };
+    let ipv6_map = if ipv6_addresses.is_empty() { None } else { Some(database.ipv6_map()?) };
+
+    let mut ipv4_countries = ipv4_map.iter().flat_map(|m| m.get_many_from_addresses(&ipv4_addresses));
+    let mut ipv6_countries = ipv6_map.iter().flat_map(|m| m.get_many_from_addresses(&ipv6_addresses));
+
+    Ok(addresses
+        .iter()
+        .map(|address| match address {
+            IpAddr::V4(_) => ipv4_countries.next().flatten().cloned(),
+            IpAddr::V6(_) => ipv6_countries.next().flatten().cloned(),
+        })
+        .collect())
+}
+
+/// Writes each of the given addresses and its country as a GeoJSON feature collection.
+///
+/// The country data contains no coordinates, so every feature has a `null` geometry. Its properties are the address,
+/// and the country's alpha-2 code, name, and numeric code, each of which is `null` if unknown.
+///
+/// # Errors
+///
+/// This function will return an error if a source map could not be parsed or the output could not be written, or
+/// [`AddressesUnmapped`](<CliError::AddressesUnmapped>) if any address was unmapped.
+fn run_geojson(out: &mut impl Write, addresses: &[IpAddr], database: &GeoDatabase) -> Result<()> {
+    let countries = self::resolve_all(addresses, database)?;
+    let unmapped = countries.iter().filter(|c| c.is_none()).count();
+    let features = addresses
+        .iter()
+        .zip(&countries)
+        .map(|(&address, country)| {
+            let code = country.as_ref().map(MaybeCountry::code).filter(|c| *c != CountryCode::Unassigned);
+            let country = country.as_ref().and_then(MaybeCountry::country);
+            let name = country.map(|c| &*c.name);
+            let numeric = country.and_then(|c| c.numeric);
+
+            Feature { kind: "Feature", geometry: None, properties: FeatureProperties { address, code, name, numeric } }
+        })
+        .collect();
+
+    serde_json::to_writer(&mut *out, &FeatureCollection { kind: "FeatureCollection", features })
+        .map_err(std::io::Error::from)?;
+
+    writeln!(out)?;
+    out.flush()?;
+
+    if unmapped > 0 { Err(CliError::AddressesUnmapped(unmapped)) } else { Ok(()) }
+}
+
+/// Reads a list of newline-separated IP addresses from the standard input, skipping blank lines.
+///
+/// # Errors
+///
+/// This function will return an error if the standard input could not be read or contains an invalid address.
+fn read_addresses() -> anyhow::Result<Box<[IpAddr]>> {
+    let mut addresses = Vec::new();
+
+    for (index, line) in std::io::stdin().lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let Ok(address) = line.parse() else {
+            bail!("invalid ip address '{line}' on line {}", index + 1);
+        };
+
+        addresses.push(address);
+    }
+
+    Ok(addresses.into_boxed_slice())
+}
+
+/// Looks up the addresses of the given hostname using the system's DNS resolver, in the order that they are returned.
+///
+/// # Errors
+///
+/// This function will return an error if the hostname could not be resolved or has no addresses.
+fn lookup_addresses(hostname: &str) -> anyhow::Result<Box<[IpAddr]>> {
+    let mut addresses = Vec::new();
+    // The port is required by the lookup, but is otherwise ignored.
+    let lookup = (hostname, 0).to_socket_addrs().with_context(|| format!("unable to resolve hostname '{hostname}'"))?;
+
+    for address in lookup {
+        if !addresses.contains(&address.ip()) {
+            addresses.push(address.ip());
+        }
+    }
+
+    if addresses.is_empty() {
+        bail!("the hostname '{hostname}' has no addresses");
+    }
+
+    Ok(addresses.into_boxed_slice())
+}
+
+/// Writes the requested fields of the given country.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn print_country(
+    out: &mut (impl Write + ?Sized),
+    country: &MaybeCountry,
+    Fields { name, code, numeric, timezone, unassigned }: Fields<'_>,
+) -> std::io::Result<()> {
     match country {
         MaybeCountry::Present(country) => {
             if name {
-                println!("Country: {}", country.name);
+                writeln!(out, "Country: {}", country.name)?;
             }
             if code {
-                println!("Alpha-2: {}", country.code);
+                writeln!(out, "Alpha-2: {}", country.code)?;
             }
             if numeric {
-                println!("Numeric: {}", country.numeric);
+                match country.numeric {
+                    Some(numeric) => writeln!(out, "Numeric: {numeric}")?,
+                    None => writeln!(out, "Numeric: N/A")?,
+                }
+            }
+            if timezone && country.timezones.is_empty() {
+                writeln!(out, "Timezones: N/A")?;
+            } else if timezone {
+                writeln!(out, "Timezones: {}", country.timezones.join(", "))?;
             }
         }
         MaybeCountry::Missing(country_code) => {
             if name {
-                println!("Country: N/A");
+                writeln!(out, "Country: N/A")?;
             }
             if code {
-                println!("Alpha-2: {country_code}");
+                writeln!(out, "Alpha-2: {}", country_code.display_with(unassigned))?;
             }
             if numeric {
-                println!("Numeric: N/A");
+                writeln!(out, "Numeric: N/A")?;
+            }
+            if timezone {
+                writeln!(out, "Timezones: N/A")?;
             }
         }
     }
 
     Ok(())
 }
+
+/// Writes the details of the map entry that an address was resolved from.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn print_explanation<A>(
+    out: &mut impl Write,
+    index: usize,
+    block: &IpAddrBlock<A>,
+    country: &MaybeCountry,
+    unassigned: &str,
+) -> std::io::Result<()>
+where
+    A: Address + Display,
+{
+    writeln!(out, "Block: {} .. {}", block.start(), block.end())?;
+    writeln!(out, "Index: {index}")?;
+    writeln!(out, "Source code: {}", country.code().display_with(unassigned))?;
+
+    match country {
+        MaybeCountry::Present(country) => writeln!(out, "Resolved: {}", country.name),
+        MaybeCountry::Missing(_) => writeln!(out, "Resolved: N/A"),
+    }
+}
+
+/// Writes the total number of blocks and addresses assigned to the given country within the given map.
+///
+/// # Errors
+///
+/// This function will return an error if the output could not be written.
+fn print_stats<A: Address>(
+    out: &mut impl Write,
+    map: &IpAddrBlockMap<A, MaybeCountry>,
+    country: &MaybeCountry,
+) -> std::io::Result<()> {
+    let filter = Filter::Code(country.code());
+    let (blocks, addresses) = map
+        .iter()
+        .filter(|(_, c)| filter.test_maybe(c).unwrap_or(false))
+        .fold((0_usize, 0_u128), |(blocks, addresses), (b, _)| {
+            (blocks + 1, addresses.saturating_add(b.address_count()))
+        });
+
+    writeln!(out, "Blocks: {}", crate::number::grouped(blocks as u128))?;
+    writeln!(out, "Addresses: {}", crate::number::grouped(addresses))
+}
+
+/// Returns the embedded IPv4 address if the given address is an IPv4-mapped or IPv4-compatible IPv6 address.
+///
+/// The IPv6 unspecified and loopback addresses are technically IPv4-compatible, but are never unmapped.
+#[must_use]
+pub fn unmap_address(address: IpAddr) -> IpAddr {
+    let IpAddr::V6(ip) = address else { return address };
+
+    if ip.is_unspecified() || ip.is_loopback() {
+        return address;
+    }
+
+    ip.to_ipv4().map_or(address, IpAddr::V4)
+}
+
+/// Returns a message describing an unmapped address, including the nearest mapped ranges surrounding it.
+fn unmapped_message<A: Address + Display>(map: &IpAddrBlockMap<A, MaybeCountry>, address: A) -> String {
+    let describe = |entry: Option<(&IpAddrBlock<A>, &MaybeCountry)>| {
+        entry.map_or_else(|| "none".to_string(), |(b, c)| format!("{} .. {} ({})", b.start(), b.end(), c.code()))
+    };
+    let (below, above) = map.surrounding(address);
+
+    format!(
+        "the given ip address is unmapped; nearest ranges are {} below and {} above",
+        describe(below),
+        describe(above)
+    )
+}
@@ -0,0 +1,207 @@
+use std::cell::OnceCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use geolocate_core::prelude::{Country, CountryCode, Ipv4AddrBlock, Ipv6AddrBlock};
+
+use crate::error::Result;
+use crate::map::{MaybeCountry, ParseOptions};
+use crate::{Ipv4CountryMap, Ipv6CountryMap};
+
+/// The ways that countries sharing an alpha-2 code within the country data may be handled.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Fail to load the country data.
+    Error,
+    /// Print a warning to the standard error, and keep the last country with the code.
+    #[default]
+    Warn,
+    /// Keep the first country with the code.
+    First,
+    /// Keep the last country with the code.
+    Last,
+}
+
+/// The country data and IP source data shared by every command.
+///
+/// Each IP source data file is only parsed the first time that its map is requested, and is reused afterwards.
+#[derive(Debug)]
+pub struct GeoDatabase {
+    /// The known countries, keyed by their alpha-2 codes.
+    countries: HashMap<CountryCode, Rc<Country>>,
+    /// The known countries, keyed by their alpha-3 codes, if codes are being unified.
+    ///
+    /// These are kept separate so that each country is still only listed once.
+    alpha3_countries: HashMap<CountryCode, Rc<Country>>,
+    /// The file to source country-to-IPv4 address data from.
+    ipv4_source: Box<Path>,
+    /// The file to source country-to-IPv6 address data from.
+    ipv6_source: Box<Path>,
+    /// The options used to parse the IP source data.
+    options: ParseOptions,
+    /// The parsed IPv4 map, if it has been requested.
+    ipv4_map: OnceCell<Ipv4CountryMap>,
+    /// The parsed IPv6 map, if it has been requested.
+    ipv6_map: OnceCell<Ipv6CountryMap>,
+}
+
+impl GeoDatabase {
+    /// Creates a new [`GeoDatabase`] from the given countries and IP source data files.
+    ///
+    /// If `unify_codes` is set, countries may also be resolved using their alpha-3 codes. Countries that share an
+    /// alpha-2 code are handled using the given policy.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if two countries share an alpha-2 code and the policy is
+    /// [`DuplicatePolicy::Error`].
+    pub fn new(
+        countries: Box<[Country]>,
+        unify_codes: bool,
+        duplicates: DuplicatePolicy,
+        ipv4_source: Box<Path>,
+        ipv6_source: Box<Path>,
+        options: ParseOptions,
+    ) -> Result<Self> {
+        let mut table = HashMap::<CountryCode, Rc<Country>>::with_capacity(countries.len());
+
+        for country in countries {
+            let code = country.code;
+
+            match (table.entry(code), duplicates) {
+                (Entry::Vacant(entry), _) => {
+                    entry.insert(Rc::new(country));
+                }
+                (Entry::Occupied(_), DuplicatePolicy::Error) => {
+                    return Err(anyhow!("duplicate country code '{code}' within the country data").into());
+                }
+                (Entry::Occupied(mut entry), DuplicatePolicy::Warn) => {
+                    eprintln!("warning: duplicate country code '{code}' within the country data; keeping the last");
+
+                    entry.insert(Rc::new(country));
+                }
+                (Entry::Occupied(_), DuplicatePolicy::First) => {}
+                (Entry::Occupied(mut entry), DuplicatePolicy::Last) => {
+                    entry.insert(Rc::new(country));
+                }
+            }
+        }
+
+        let countries = table;
+        let alpha3_countries = if unify_codes {
+            countries.values().filter_map(|c| Some((c.alpha3?, Rc::clone(c)))).collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            countries,
+            alpha3_countries,
+            ipv4_source,
+            ipv6_source,
+            options,
+            ipv4_map: OnceCell::new(),
+            ipv6_map: OnceCell::new(),
+        })
+    }
+
+    /// Returns the file that IPv4 source data is read from.
+    #[inline]
+    #[must_use]
+    pub fn ipv4_source(&self) -> &Path {
+        &self.ipv4_source
+    }
+
+    /// Returns the file that IPv6 source data is read from.
+    #[inline]
+    #[must_use]
+    pub fn ipv6_source(&self) -> &Path {
+        &self.ipv6_source
+    }
+
+    /// Returns the options used to parse the IP source data.
+    #[inline]
+    #[must_use]
+    pub const fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    /// Returns the country with the given alpha-2 code, or alpha-3 code if codes are being unified.
+    ///
+    /// Alpha-4 codes, which refer to countries that no longer exist, resolve to their successor's country if they have
+    /// one.
+    #[must_use]
+    pub fn resolve_country(&self, code: CountryCode) -> Option<Rc<Country>> {
+        self.countries
+            .get(&code)
+            .or_else(|| self.alpha3_countries.get(&code))
+            .or_else(|| self.countries.get(&code.successor_alpha2()?))
+            .cloned()
+    }
+
+    /// Returns an iterator over every known country, in no particular order.
+    #[inline]
+    pub fn countries(&self) -> impl Iterator<Item = &Country> {
+        self.countries.values().map(Rc::as_ref)
+    }
+
+    /// Returns the IPv4 map, parsing it if it has not yet been parsed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the IPv4 source data could not be read or parsed.
+    pub fn ipv4_map(&self) -> Result<&Ipv4CountryMap> {
+        if let Some(map) = self.ipv4_map.get() {
+            return Ok(map);
+        }
+
+        let map = crate::map::parse_ipv4_map_file(&self.ipv4_source, None, self.options, |c| self.resolve_country(c))?;
+
+        Ok(self.ipv4_map.get_or_init(|| map))
+    }
+
+    /// Returns the IPv6 map, parsing it if it has not yet been parsed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the IPv6 source data could not be read or parsed.
+    pub fn ipv6_map(&self) -> Result<&Ipv6CountryMap> {
+        if let Some(map) = self.ipv6_map.get() {
+            return Ok(map);
+        }
+
+        let map = crate::map::parse_ipv6_map_file(&self.ipv6_source, None, self.options, |c| self.resolve_country(c))?;
+
+        Ok(self.ipv6_map.get_or_init(|| map))
+    }
+
+    /// Returns the country assigned to the given address, or [`None`] if it is not mapped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the address' source data could not be read or parsed.
+    pub fn resolve_address(&self, address: IpAddr) -> Result<Option<&MaybeCountry>> {
+        match address {
+            IpAddr::V4(address) => Ok(self.ipv4_map()?.get_from_address(address)),
+            IpAddr::V6(address) => Ok(self.ipv6_map()?.get_from_address(address)),
+        }
+    }
+
+    /// Returns every IPv4 and IPv6 block assigned to the country with the given code, in sorted order.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if either source data file could not be read or parsed.
+    #[allow(clippy::type_complexity)]
+    pub fn blocks_for_country(&self, code: CountryCode) -> Result<(Box<[Ipv4AddrBlock]>, Box<[Ipv6AddrBlock]>)> {
+        let ipv4_blocks = self.ipv4_map()?.iter().filter(|(_, c)| c.code() == code).map(|(b, _)| *b).collect();
+        let ipv6_blocks = self.ipv6_map()?.iter().filter(|(_, c)| c.code() == code).map(|(b, _)| *b).collect();
+
+        Ok((ipv4_blocks, ipv6_blocks))
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::Path;
+
+use geolocate_core::asn::Asn;
+
+use crate::ip::Reservation;
+
+/// The exit code returned when a valid address or number is not mapped to any country.
+pub const UNMAPPED_EXIT_CODE: u8 = 2;
+
+/// A result with a default error type.
+pub type Result<T, E = CliError> = std::result::Result<T, E>;
+
+/// An error returned by a command.
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// A source data file could not be found.
+    #[error("unable to locate file '{}'", .0.to_string_lossy())]
+    SourceMissing(Box<Path>),
+    /// A source data file could not be read or parsed.
+    #[error("unable to parse file '{}'", path.to_string_lossy())]
+    ParseFailed {
+        /// The path of the source data file.
+        path: Box<Path>,
+        /// The error that caused parsing to fail.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// No country matched a filter, with the filter being described by the contained string.
+    #[error("unable to find {0}")]
+    CountryNotFound(Box<str>),
+    /// An address was valid, but was not mapped to any country.
+    #[error("the ip address {0} is unmapped")]
+    AddressUnmapped(IpAddr),
+    /// An address was valid, but was within a special-purpose range that is never mapped to a country.
+    #[error("the ip address {0} is reserved ({1})")]
+    AddressReserved(IpAddr, Reservation),
+    /// Some of several resolved addresses were not mapped to any country, with the number of such addresses being
+    /// contained.
+    #[error("{0} of the ip addresses are unmapped")]
+    AddressesUnmapped(usize),
+    /// An autonomous system number was valid, but was not mapped to any country.
+    #[error("the autonomous system number AS{0} is unmapped")]
+    AsnUnmapped(Asn),
+    /// The output could not be written.
+    #[error("unable to write output")]
+    WriteFailed(#[from] std::io::Error),
+    /// Any other error, such as a failure to read the standard input or draw to the terminal.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl CliError {
+    /// Creates a new [`CliError`] for a source data file that failed to parse, returning
+    /// [`SourceMissing`](<CliError::SourceMissing>) if the failure was caused by the file not existing.
+    #[must_use]
+    pub fn parse_failed(path: &Path, error: anyhow::Error) -> Self {
+        if error.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == ErrorKind::NotFound) {
+            return Self::SourceMissing(path.into());
+        }
+
+        Self::ParseFailed { path: path.into(), source: error.into() }
+    }
+
+    /// Returns whether this error reports that a valid address or number was not mapped to any country.
+    ///
+    /// Commands report these outcomes themselves, so they are only converted into [`UNMAPPED_EXIT_CODE`] rather than
+    /// displayed.
+    #[must_use]
+    pub const fn is_unmapped(&self) -> bool {
+        matches!(
+            self,
+            Self::AddressUnmapped(_) | Self::AddressReserved(..) | Self::AddressesUnmapped(_) | Self::AsnUnmapped(_)
+        )
+    }
+}
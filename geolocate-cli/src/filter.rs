@@ -1,6 +1,10 @@
-use anyhow::{anyhow, Result};
-use geolocate_core::country::{Country, CountryCode};
+use std::str::FromStr;
 
+use anyhow::{Error, Result};
+use geolocate_core::country::{Country, CountryCode, NumericCode};
+use geolocate_core::ip::{Address, IpAddrBlock, IpAddrBlockMap};
+
+use crate::error::CliError;
 use crate::map::MaybeCountry;
 
 /// A country filter for usage in commands.
@@ -13,10 +17,37 @@ pub enum Filter<'c> {
     /// Filters for a country with the given alpha-2 code.
     Code(CountryCode),
     /// Filters for a country with the given numeric code.
-    Numeric(u16),
+    Numeric(NumericCode),
+    /// Filters for a country whose code starts with the given prefix, ignoring case.
+    CodePrefix(Box<str>),
+    /// Filters for a country that matches any of the given filters.
+    Any(Box<[Self]>),
+    /// Filters for a country that matches all of the given filters.
+    All(Box<[Self]>),
 }
 
 impl Filter<'_> {
+    /// Combines the given filters into a single filter, returning [`None`] if no filters are given.
+    ///
+    /// Multiple filters are combined using [`Filter::All`] if `match_all` is set, and [`Filter::Any`] otherwise.
+    #[must_use]
+    pub fn combine(mut filters: Vec<Self>, match_all: bool) -> Option<Self> {
+        match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ if match_all => Some(Self::All(filters.into_boxed_slice())),
+            _ => Some(Self::Any(filters.into_boxed_slice())),
+        }
+    }
+
+    /// Returns the alternatives of this filter, each of which is matched independently.
+    ///
+    /// This is the inner list of a [`Filter::Any`], or this filter alone otherwise.
+    #[must_use]
+    pub fn alternatives(&self) -> &[Self] {
+        if let Self::Any(filters) = self { filters } else { std::slice::from_ref(self) }
+    }
+
     /// Checks whether the given country matches this filter.
     #[must_use]
     pub fn test(&self, country: &Country) -> bool {
@@ -24,7 +55,10 @@ impl Filter<'_> {
             Self::Country(c) => country == *c,
             Self::Name(name) => &country.name == name,
             Self::Code(code) => &country.code == code,
-            Self::Numeric(numeric) => &country.numeric == numeric,
+            Self::Numeric(numeric) => country.numeric.as_ref() == Some(numeric),
+            Self::CodePrefix(prefix) => self::has_code_prefix(country.code, prefix),
+            Self::Any(filters) => filters.iter().any(|f| f.test(country)),
+            Self::All(filters) => filters.iter().all(|f| f.test(country)),
         }
     }
 
@@ -34,36 +68,209 @@ impl Filter<'_> {
         match (country, self) {
             (MaybeCountry::Present(country), _) => Some(self.test(country)),
             (MaybeCountry::Missing(code_a), Self::Code(code_b)) => Some(code_a == code_b),
+            (MaybeCountry::Missing(code), Self::CodePrefix(prefix)) => Some(self::has_code_prefix(*code, prefix)),
+            (_, Self::Any(filters)) => {
+                let mut results = filters.iter().map(|f| f.test_maybe(country));
+
+                if results.clone().any(|r| r == Some(true)) {
+                    Some(true)
+                } else {
+                    results.all(|r| r.is_some()).then_some(false)
+                }
+            }
+            (_, Self::All(filters)) => {
+                let mut results = filters.iter().map(|f| f.test_maybe(country));
+
+                if results.clone().any(|r| r == Some(false)) {
+                    Some(false)
+                } else {
+                    results.all(|r| r.is_some()).then_some(true)
+                }
+            }
             _ => None,
         }
     }
 }
 
-impl From<&str> for Filter<'_> {
-    fn from(value: &str) -> Self {
+impl FromStr for Filter<'_> {
+    type Err = Error;
+
+    /// Parses a filter from the given string.
+    ///
+    /// The string may be prefixed with `name:`, `code:`, or `num:` to explicitly filter by a country's name, alpha-2
+    /// code, or numeric code respectively, with prefixed codes being case-insensitive. Otherwise, it is parsed as a
+    /// numeric code, then as an alpha-2 code, and is finally treated as a name.
+    ///
+    /// Numeric codes are the ISO 3166-1 numeric codes given by the country data, and may include leading zeros, so
+    /// both `num:36` and `num:036` filter for Australia.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(name) = value.strip_prefix("name:") {
+            return Ok(Self::Name(name.trim().into()));
+        }
+        if let Some(code) = value.strip_prefix("code:") {
+            return Ok(Self::Code(code.trim().to_ascii_uppercase().parse()?));
+        }
+        if let Some(numeric) = value.strip_prefix("num:") {
+            return Ok(Self::Numeric(numeric.trim().parse()?));
+        }
+
         if let Ok(numeric) = value.parse() {
-            return Filter::Numeric(numeric);
+            return Ok(Self::Numeric(numeric));
         }
         if let Ok(code) = value.parse() {
-            return Filter::Code(code);
+            return Ok(Self::Code(code));
         }
 
-        Filter::Name(value.into())
+        Ok(Self::Name(value.into()))
     }
 }
 
 /// Attempts to find a country using the given filter.
 ///
+/// If multiple countries match, the one with the lowest numeric code is chosen, followed by those without a numeric
+/// code ordered by their alpha-2 codes, so that the result never depends on the iteration order of the given countries.
+///
 /// # Errors
 ///
 /// This function will return an error if the country could not be found.
-pub fn find_country<'c>(filter: &Filter, mut iter: impl Iterator<Item = &'c Country>) -> Result<Country> {
-    let country = iter.find(|c| filter.test(c)).ok_or_else(|| match filter {
-        Filter::Country(country) => anyhow!("unable to find country '{}'", country.name),
-        Filter::Name(name) => anyhow!("unable to find country '{name}'"),
-        Filter::Code(code) => anyhow!("unable to find country '{code}'"),
-        Filter::Numeric(numeric) => anyhow!("unable to find country #{numeric}"),
-    });
-
-    country.cloned()
+pub fn find_country<'c>(filter: &Filter, iter: impl Iterator<Item = &'c Country>) -> Result<Country, CliError> {
+    let country = iter.filter(|c| filter.test(c)).min_by_key(|c| (c.numeric.is_none(), c.numeric, c.code));
+
+    country.cloned().ok_or_else(|| self::not_found(filter))
+}
+
+/// Attempts to find every country that matches the given filter, sorted by their codes.
+///
+/// A filter containing a [`Filter::CodePrefix`] may match any number of countries, while any other filter only ever
+/// finds the same single country as [`find_country`].
+///
+/// # Errors
+///
+/// This function will return an error if no countries could be found.
+pub fn find_countries<'c>(
+    filter: &Filter,
+    iter: impl Iterator<Item = &'c Country>,
+) -> Result<Box<[Country]>, CliError> {
+    if !self::has_prefix_filter(filter) {
+        return self::find_country(filter, iter).map(|country| Box::new([country]) as Box<[_]>);
+    }
+
+    let mut countries: Box<[_]> = iter.filter(|c| filter.test(c)).cloned().collect();
+
+    if countries.is_empty() {
+        return Err(self::not_found(filter));
+    }
+
+    countries.sort_unstable_by_key(|c| c.code);
+
+    Ok(countries)
+}
+
+/// Returns the error produced when no country matches the given filter.
+fn not_found(filter: &Filter) -> CliError {
+    CliError::CountryNotFound(match filter {
+        Filter::Country(country) => format!("country '{}'", country.name).into(),
+        Filter::Name(name) => format!("country '{name}'").into(),
+        Filter::Code(code) => format!("country '{code}'").into(),
+        Filter::Numeric(numeric) => format!("country #{numeric}").into(),
+        Filter::CodePrefix(prefix) => format!("a country whose code starts with '{prefix}'").into(),
+        Filter::Any(_) => "a country matching any of the given filters".into(),
+        Filter::All(_) => "a country matching all of the given filters".into(),
+    })
+}
+
+/// Returns whether the given filter contains a [`Filter::CodePrefix`].
+fn has_prefix_filter(filter: &Filter) -> bool {
+    match filter {
+        Filter::CodePrefix(_) => true,
+        Filter::Any(filters) | Filter::All(filters) => filters.iter().any(self::has_prefix_filter),
+        _ => false,
+    }
+}
+
+/// Returns whether the given code starts with the given prefix, ignoring case.
+fn has_code_prefix(code: CountryCode, prefix: &str) -> bool {
+    let chars: &[char] = match &code {
+        CountryCode::Alpha2(array) => array,
+        CountryCode::Alpha3(array) => array,
+        CountryCode::Alpha4(array) => array,
+        CountryCode::Unassigned => &['?', '?'],
+    };
+
+    prefix.chars().count() <= chars.len() && prefix.chars().zip(chars).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+/// Returns an iterator over the entries of the given map whose countries match any of the `only` filters, and none of
+/// the `exclude` filters.
+///
+/// If no `only` filters are given, every entry not matching an `exclude` filter is kept. The entries are borrowed from
+/// the map, so it is never copied.
+pub fn filter_entries<'m, A: Address>(
+    map: &'m IpAddrBlockMap<A, MaybeCountry>,
+    only: &'m [Filter<'m>],
+    exclude: &'m [Filter<'m>],
+) -> impl Iterator<Item = (&'m IpAddrBlock<A>, &'m MaybeCountry)> {
+    let matches = |filters: &[Filter<'_>], country| filters.iter().any(|f| f.test_maybe(country).unwrap_or(false));
+
+    map.iter().filter(move |(_, country)| (only.is_empty() || matches(only, country)) && !matches(exclude, country))
+}
+
+#[cfg(test)]
+mod tests {
+    use geolocate_core::country::{Country, CountryCode, NumericCode};
+
+    use super::Filter;
+
+    /// Returns a synthetic country with the given name, code, and numeric code.
+    fn country(name: &str, code: &str, numeric: Option<u16>) -> Country {
+        Country::new(name, code.parse().unwrap(), numeric.and_then(NumericCode::new))
+    }
+
+    #[test]
+    fn shared_name_resolves_to_lowest_numeric_code() {
+        let countries = [self::country("Atlantis", "XB", Some(901)), self::country("Atlantis", "XA", Some(900))];
+        let filter = Filter::Name("Atlantis".into());
+
+        let forward = super::find_country(&filter, countries.iter()).unwrap();
+        let reverse = super::find_country(&filter, countries.iter().rev()).unwrap();
+
+        assert_eq!(forward, countries[1]);
+        assert_eq!(reverse, countries[1]);
+    }
+
+    #[test]
+    fn shared_name_prefers_numeric_codes_then_lowest_code() {
+        let countries = [
+            self::country("Atlantis", "XC", None),
+            self::country("Atlantis", "XB", None),
+            self::country("Atlantis", "XD", Some(999)),
+        ];
+        let filter = Filter::Name("Atlantis".into());
+
+        assert_eq!(super::find_country(&filter, countries.iter()).unwrap(), countries[2]);
+        assert_eq!(super::find_country(&filter, countries[.. 2].iter()).unwrap(), countries[1]);
+        assert_eq!(super::find_country(&filter, countries[.. 2].iter().rev()).unwrap(), countries[1]);
+    }
+
+    #[test]
+    fn code_prefix_ignores_case_and_length() {
+        let code = "BUMM".parse().unwrap();
+
+        assert!(super::has_code_prefix(code, ""));
+        assert!(super::has_code_prefix(code, "b"));
+        assert!(super::has_code_prefix(code, "bUmM"));
+        assert!(!super::has_code_prefix(code, "BUMMX"));
+        assert!(!super::has_code_prefix(code, "MM"));
+        assert!(super::has_code_prefix(CountryCode::Unassigned, "??"));
+    }
+
+    #[test]
+    fn numeric_prefix_parses_numeric_codes() {
+        let numeric = NumericCode::new(36).unwrap();
+
+        assert_eq!("num:36".parse::<Filter>().unwrap(), Filter::Numeric(numeric));
+        assert_eq!("num: 036".parse::<Filter>().unwrap(), Filter::Numeric(numeric));
+        assert_eq!("036".parse::<Filter>().unwrap(), Filter::Numeric(numeric));
+        assert!("num:AU".parse::<Filter>().is_err());
+    }
 }
@@ -1,6 +1,8 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::fmt::Display;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
+use geolocate_core::asn::Asn;
 use serde::de::{Unexpected, Visitor};
 use serde::Deserializer;
 
@@ -33,6 +35,11 @@ where
         where
             E: serde::de::Error,
         {
+            // Addresses may also be written as their integer representation.
+            if let Ok(bits) = v.parse::<u32>() {
+                return Ok(Ipv4Addr::from_bits(bits));
+            }
+
             Ipv4Addr::from_str(v).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
         }
     }
@@ -69,9 +76,157 @@ where
         where
             E: serde::de::Error,
         {
+            // Addresses may also be written as their integer representation.
+            if let Ok(bits) = v.parse::<u128>() {
+                return Ok(Ipv6Addr::from_bits(bits));
+            }
+
             Ipv6Addr::from_str(v).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
         }
     }
 
     deserializer.deserialize_str(Ipv6Visitor)
 }
+
+/// Deserializes an autonomous system number, which may be prefixed with `AS`.
+///
+/// # Errors
+///
+/// This function will return an error if the value cannot be deserialized.
+pub fn deserialize_asn<'de, D>(deserializer: D) -> Result<Asn, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct AsnVisitor;
+
+    impl Visitor<'_> for AsnVisitor {
+        type Value = Asn;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "a valid autonomous system number")
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            Asn::try_from(v).map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error,
+        {
+            geolocate_core::asn::parse_asn(v).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+        }
+    }
+
+    // Numbers are deserialized as strings, since they may be prefixed with `AS`.
+    deserializer.deserialize_str(AsnVisitor)
+}
+
+/// The kinds of special-purpose address ranges that are never assigned to a country.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reservation {
+    /// A private network address, such as `192.168.0.0/16` or the IPv6 unique local range `fc00::/7`.
+    Private,
+    /// A carrier-grade NAT address within `100.64.0.0/10`.
+    Shared,
+    /// A loopback address, such as `127.0.0.1` or `::1`.
+    Loopback,
+    /// A link-local address, such as `169.254.0.0/16` or `fe80::/10`.
+    LinkLocal,
+    /// An address within `0.0.0.0/8`, or the IPv6 unspecified address.
+    Unspecified,
+    /// A multicast address.
+    Multicast,
+    /// The IPv4 broadcast address.
+    Broadcast,
+    /// An address reserved for use within documentation.
+    Documentation,
+    /// An address reserved for network benchmarking within `198.18.0.0/15`.
+    Benchmarking,
+    /// An address reserved for future use within `240.0.0.0/4`.
+    Future,
+}
+
+impl Display for Reservation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Private => "private",
+            Self::Shared => "shared",
+            Self::Loopback => "loopback",
+            Self::LinkLocal => "link-local",
+            Self::Unspecified => "unspecified",
+            Self::Multicast => "multicast",
+            Self::Broadcast => "broadcast",
+            Self::Documentation => "documentation",
+            Self::Benchmarking => "benchmarking",
+            Self::Future => "reserved for future use",
+        })
+    }
+}
+
+/// Returns the kind of special-purpose range that contains the given address, or [`None`] if it may be assigned to a
+/// country.
+#[must_use]
+pub const fn reservation(address: IpAddr) -> Option<Reservation> {
+    match address {
+        IpAddr::V4(ip) => self::ipv4_reservation(ip),
+        IpAddr::V6(ip) => self::ipv6_reservation(ip),
+    }
+}
+
+/// Returns the kind of special-purpose range that contains the given IPv4 address.
+const fn ipv4_reservation(ip: Ipv4Addr) -> Option<Reservation> {
+    let [a, b, ..] = ip.octets();
+
+    let reservation = if ip.is_private() {
+        Reservation::Private
+    } else if a == 100 && (b & 0b1100_0000) == 64 {
+        Reservation::Shared
+    } else if ip.is_loopback() {
+        Reservation::Loopback
+    } else if ip.is_link_local() {
+        Reservation::LinkLocal
+    } else if a == 0 {
+        Reservation::Unspecified
+    } else if ip.is_multicast() {
+        Reservation::Multicast
+    } else if ip.is_broadcast() {
+        Reservation::Broadcast
+    } else if ip.is_documentation() {
+        Reservation::Documentation
+    } else if a == 198 && (b & 0b1111_1110) == 18 {
+        Reservation::Benchmarking
+    } else if a >= 240 {
+        Reservation::Future
+    } else {
+        return None;
+    };
+
+    Some(reservation)
+}
+
+/// Returns the kind of special-purpose range that contains the given IPv6 address.
+const fn ipv6_reservation(ip: Ipv6Addr) -> Option<Reservation> {
+    let [a, b, ..] = ip.segments();
+
+    let reservation = if ip.is_unique_local() {
+        Reservation::Private
+    } else if ip.is_loopback() {
+        Reservation::Loopback
+    } else if ip.is_unicast_link_local() {
+        Reservation::LinkLocal
+    } else if ip.is_unspecified() {
+        Reservation::Unspecified
+    } else if ip.is_multicast() {
+        Reservation::Multicast
+    } else if a == 0x2001 && b == 0x0DB8 {
+        Reservation::Documentation
+    } else {
+        return None;
+    };
+
+    Some(reservation)
+}
@@ -6,25 +6,55 @@
 #![allow(clippy::module_name_repetitions)]
 #![feature(iter_intersperse)]
 
-use std::collections::HashMap;
 use std::path::Path;
+use std::process::ExitCode;
 
 use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
-use geolocate_core::prelude::{Country, CountryCode, Ipv4AddrBlockMap, Ipv6AddrBlockMap};
-use map::MaybeCountry;
+use database::{DuplicatePolicy, GeoDatabase};
+use error::{CliError, UNMAPPED_EXIT_CODE};
+use geolocate_core::prelude::{Country, Ipv4AddrBlockMap, Ipv6AddrBlockMap};
+use map::{MaybeCountry, ParseOptions};
 
+/// Provides the loaded country and IP source data shared by commands.
+pub mod database;
+/// Provides the error type returned by commands.
+pub mod error;
 /// Provides country filtering for commands.
 pub mod filter;
-/// Provides IP address deserializers.
+/// Provides IP address and autonomous system number deserializers.
 pub mod ip;
 /// Provides IP-block-map deserializers.
 pub mod map;
+/// Provides number formatting shared by commands.
+pub mod number;
+/// Provides the output destination shared by commands.
+pub mod output;
+/// Provides support for fetching source data over HTTP.
+#[cfg(feature = "http")]
+pub mod remote;
 
 /// Provides implementations for each command.
 pub mod command {
+    /// The asn command.
+    pub mod asn;
+    /// The browse command.
+    #[cfg(feature = "tui")]
+    pub mod browse;
+    /// The check command.
+    pub mod check;
     /// The count command.
     pub mod count;
+    /// The diff command.
+    pub mod diff;
+    /// The index command.
+    #[cfg(feature = "index")]
+    pub mod index;
+    /// The info command.
+    pub mod info;
+    /// The init command.
+    #[cfg(feature = "embedded-countries")]
+    pub mod init;
     /// The list command.
     pub mod list;
     /// The resolve command.
@@ -38,6 +68,7 @@ pub type Ipv6CountryMap = Ipv6AddrBlockMap<MaybeCountry>;
 
 /// The application's command-line arguments.
 #[non_exhaustive]
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Parser)]
 #[command(about, author, version, long_about = None)]
 pub struct Arguments {
@@ -47,9 +78,58 @@ pub struct Arguments {
     /// The file to source country-to-IPv6 address data from.
     #[arg(short = '6', long = "ipv6-source-data", default_value = "/usr/share/tor/geoip6")]
     pub ipv6_source: Box<Path>,
-    /// The file to source country data from.
-    #[arg(short = 'c', long = "country-source-data", default_value = "./data/countries.json")]
-    pub country_source: Box<Path>,
+    /// The file to source country data from, or '-' to read it from the standard input.
+    ///
+    /// The file's format is chosen from its extension, and may be JSON, TOML, or YAML if the respective features are
+    /// enabled. The standard input is always read as JSON.
+    ///
+    /// If not given, this defaults to './data/countries.json', falling back to the embedded country data if that file
+    /// does not exist.
+    #[arg(short = 'c', long = "country-source-data")]
+    pub country_source: Option<Box<Path>>,
+    /// The character that separates fields within the IP source data.
+    #[arg(long = "delimiter", default_value = ",", value_parser = crate::map::parse_delimiter)]
+    pub delimiter: u8,
+    /// Treat the first line of the IP source data as a header row naming its `start`, `end`, and `country` columns.
+    #[arg(long = "headers")]
+    pub headers: bool,
+    /// Read each IP source data block from a single hyphenated `start-end` column, rather than two columns.
+    #[arg(long = "range-column")]
+    pub range_column: bool,
+    /// Read the IP source data as RIR delegated-stats files, such as those published by RIPE NCC and APNIC.
+    #[arg(long = "delegated", conflicts_with_all = ["delimiter", "headers", "range_column"])]
+    pub delegated: bool,
+    /// Fail if any IP source data record's country code could not be parsed or resolved using the country data.
+    ///
+    /// By default, such records are kept, and are reported using their country code.
+    #[arg(long = "strict")]
+    pub strict: bool,
+    /// Treat IP source data records whose country code is prefixed with `!`, such as `!DE`, as exclusions.
+    ///
+    /// Each excluded block is removed from the blocks assigned to the same country, regardless of where the records
+    /// appear within the file.
+    #[arg(long = "exclusions", conflicts_with = "delegated")]
+    pub exclusions: bool,
+    /// Resolve IP source data records that use a country's alpha-3 code to the same country as its alpha-2 code.
+    ///
+    /// This requires the country data to contain each country's `alpha3` code, and merges the blocks of both codes
+    /// into a single entry wherever countries are grouped.
+    #[arg(long = "unify-codes")]
+    pub unify_codes: bool,
+    /// How to handle countries that share an alpha-2 code within the country data.
+    #[arg(long = "on-duplicate", value_enum, default_value = "warn")]
+    pub on_duplicate: DuplicatePolicy,
+    /// The language to display country names in, if the country data contains names in that language.
+    #[arg(long = "lang")]
+    pub language: Option<Box<str>>,
+    /// The base URL to fetch the IPv4 and IPv6 source data from, using the file names of their source paths.
+    #[cfg(feature = "http")]
+    #[arg(long = "source-url")]
+    pub source_url: Option<Box<str>>,
+    /// Display the progress of reading IP source data files, if the standard error is a terminal.
+    #[cfg(feature = "progress")]
+    #[arg(long = "progress")]
+    pub progress: bool,
 
     /// The command to run.
     #[command(subcommand)]
@@ -61,54 +141,201 @@ pub struct Arguments {
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Subcommand)]
 #[command(about, author, long_about = None)]
 pub enum Command {
+    /// Resolves an autonomous system number's country of registration.
+    Asn(crate::command::asn::Arguments),
+    /// Interactively browses countries and their assigned IP address blocks.
+    #[cfg(feature = "tui")]
+    Browse(crate::command::browse::Arguments),
+    /// Reports country codes within the IP source data that have no country data, and countries without any blocks.
+    Check(crate::command::check::Arguments),
     /// Tallies the number of IP addresses assigned per country.
     Count(crate::command::count::Arguments),
+    /// Compares two IP source data files, reporting blocks that were added, removed, or reassigned.
+    Diff(crate::command::diff::Arguments),
+    /// Writes both IP source data files into a single index file, which 'resolve --index' may search without parsing.
+    #[cfg(feature = "index")]
+    Index(crate::command::index::Arguments),
+    /// Reports where the country data and IP source data were read from, and summarizes their contents.
+    Info(crate::command::info::Arguments),
+    /// Writes the embedded country data to a file, so that it may be used or edited without any network access.
+    #[cfg(feature = "embedded-countries")]
+    Init(crate::command::init::Arguments),
     /// Lists all IP address blocks and their assigned country.
     List(crate::command::list::Arguments),
     /// Resolves a single IP address' country of origin.
     Resolve(crate::command::resolve::Arguments),
 }
 
+/// The default file to source country data from.
+pub const DEFAULT_COUNTRY_SOURCE: &str = "./data/countries.json";
+
+/// The country data that is embedded within the binary.
+#[cfg(feature = "embedded-countries")]
+pub const EMBEDDED_COUNTRIES: &str = include_str!("../data/countries.json");
+
 /// The application's entrypoint.
 ///
 /// # Errors
 ///
 /// This function will return an error if the program fails to run.
-pub fn main() -> Result<()> {
-    let arguments = Arguments::parse();
+pub fn main() -> Result<ExitCode> {
+    // Argument errors exit with the usual error code rather than clap's default, which is reserved for unmapped
+    // addresses in the 'resolve' command.
+    let arguments = Arguments::try_parse().unwrap_or_else(|error| {
+        let _ = error.print();
+
+        std::process::exit(i32::from(error.use_stderr()))
+    });
+    #[cfg(feature = "embedded-countries")]
+    if let Command::Init(command_arguments) = arguments.command {
+        crate::command::init::run(command_arguments)?;
 
-    if !std::fs::exists(&arguments.ipv4_source)? {
-        bail!("unable to locate file '{}'", arguments.ipv4_source.to_string_lossy());
+        return Ok(ExitCode::SUCCESS);
     }
-    if !std::fs::exists(&arguments.ipv6_source)? {
-        bail!("unable to locate file '{}'", arguments.ipv6_source.to_string_lossy());
+    #[cfg(feature = "http")]
+    let arguments = crate::remote::fetch_sources(arguments)?;
+
+    // The 'asn' and 'diff' commands are given their own source files, so the default sources do not need to exist.
+    let uses_sources = !matches!(arguments.command, Command::Asn(_) | Command::Diff(_));
+    // Resolving an address using an index does not read either source file.
+    #[cfg(feature = "index")]
+    let uses_sources = uses_sources && !matches!(&arguments.command, Command::Resolve(a) if a.index.is_some());
+
+    if uses_sources && !std::fs::exists(&arguments.ipv4_source)? {
+        return Err(CliError::SourceMissing(arguments.ipv4_source).into());
     }
-    if !std::fs::exists(&arguments.country_source)? {
-        bail!("unable to locate file '{}'", arguments.country_source.to_string_lossy());
+    if uses_sources && !std::fs::exists(&arguments.ipv6_source)? {
+        return Err(CliError::SourceMissing(arguments.ipv6_source).into());
     }
 
-    let file = std::fs::File::open(&arguments.country_source)?;
-    let countries: Box<[Country]> = serde_json::from_reader(file)?;
-    let countries: HashMap<CountryCode, Country> = countries.iter().map(|c| (c.code, c.clone())).collect();
-    let resolve = |code: CountryCode| -> Option<Country> { countries.get(&code).cloned() };
-
-    match arguments.command {
-        Command::Count(command_arguments) => crate::command::count::run(
-            command_arguments,
-            &arguments.ipv4_source,
-            &arguments.ipv6_source,
-            resolve,
-            countries.values(),
-        ),
-        Command::List(command_arguments) => crate::command::list::run(
-            command_arguments,
-            &arguments.ipv4_source,
-            &arguments.ipv6_source,
-            resolve,
-            countries.values(),
-        ),
-        Command::Resolve(command_arguments) => {
-            crate::command::resolve::run(command_arguments, &arguments.ipv4_source, &arguments.ipv6_source, resolve)
+    let (mut countries, country_source) =
+        self::load_countries(arguments.country_source.as_deref(), &arguments.command)?;
+
+    if let Some(language) = arguments.language.as_deref() {
+        for country in &mut countries {
+            country.name = country.localized_name(language).into();
+        }
+    }
+
+    let options = ParseOptions {
+        delimiter: arguments.delimiter,
+        has_headers: arguments.headers,
+        range_column: arguments.range_column,
+        delegated: arguments.delegated,
+        strict: arguments.strict,
+        exclusions: arguments.exclusions,
+        #[cfg(feature = "progress")]
+        progress: arguments.progress,
+        ..Default::default()
+    };
+    let database = GeoDatabase::new(
+        countries,
+        arguments.unify_codes,
+        arguments.on_duplicate,
+        arguments.ipv4_source,
+        arguments.ipv6_source,
+        options,
+    )?;
+
+    let result = match arguments.command {
+        Command::Asn(command_arguments) => crate::command::asn::run(command_arguments, &database),
+        #[cfg(feature = "tui")]
+        Command::Browse(command_arguments) => crate::command::browse::run(command_arguments, &database),
+        Command::Check(command_arguments) => crate::command::check::run(command_arguments, &database),
+        Command::Count(command_arguments) => crate::command::count::run(command_arguments, &database),
+        Command::Diff(command_arguments) => crate::command::diff::run(command_arguments, &database),
+        #[cfg(feature = "index")]
+        Command::Index(command_arguments) => crate::command::index::run(command_arguments, &database),
+        Command::Info(command_arguments) => crate::command::info::run(command_arguments, country_source, &database),
+        #[cfg(feature = "embedded-countries")]
+        Command::Init(_) => unreachable!("the 'init' command is run before any data is loaded"),
+        Command::List(command_arguments) => crate::command::list::run(command_arguments, &database),
+        Command::Resolve(command_arguments) => crate::command::resolve::run(command_arguments, &database),
+    };
+
+    // Commands report unmapped addresses and numbers themselves, so they only need to be converted into an exit code.
+    match result {
+        Err(error) if error.is_unmapped() => return Ok(ExitCode::from(UNMAPPED_EXIT_CODE)),
+        result => result?,
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Loads the list of known countries from the given source.
+///
+/// A source of `-` is read from the standard input. If no source is given, the [default
+/// file](<DEFAULT_COUNTRY_SOURCE>) is used if it exists, and the [embedded data](<EMBEDDED_COUNTRIES>) otherwise.
+///
+/// The countries are returned alongside the source that they were read from, or [`None`] if the embedded data was used.
+///
+/// # Errors
+///
+/// This function will return an error if the source could not be located, read, or parsed.
+fn load_countries<'s>(source: Option<&'s Path>, command: &Command) -> Result<(Box<[Country]>, Option<&'s Path>)> {
+    if let Some(path) = source {
+        if path == Path::new("-") {
+            if matches!(command, Command::Resolve(arguments) if arguments.stdin) {
+                bail!("country data and addresses cannot both be read from the standard input");
+            }
+
+            return Ok((serde_json::from_reader(std::io::stdin().lock())?, Some(path)));
+        }
+        if !std::fs::exists(path)? {
+            return Err(CliError::SourceMissing(path.into()).into());
+        }
+
+        return Ok((self::parse_country_file(path)?, Some(path)));
+    }
+
+    let path = Path::new(DEFAULT_COUNTRY_SOURCE);
+
+    if std::fs::exists(path)? {
+        return Ok((self::parse_country_file(path)?, Some(path)));
+    }
+
+    #[cfg(feature = "embedded-countries")]
+    return Ok((serde_json::from_str(EMBEDDED_COUNTRIES)?, None));
+
+    #[cfg(not(feature = "embedded-countries"))]
+    Err(CliError::SourceMissing(path.into()).into())
+}
+
+/// Parses the list of known countries from the given file, choosing its format from the file's extension.
+///
+/// JSON and YAML files contain a list of countries, while TOML files contain a `countries` array of tables. Files
+/// without an extension are read as JSON.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be read or parsed, or if its format is not supported.
+fn parse_country_file(path: &Path) -> Result<Box<[Country]>> {
+    /// The top-level table of a TOML country file, since TOML documents cannot be arrays.
+    #[cfg(feature = "toml")]
+    #[derive(serde::Deserialize)]
+    struct TomlCountries {
+        /// The list of countries.
+        countries: Box<[Country]>,
+    }
+
+    let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+
+    match extension.as_deref() {
+        None | Some("json") => Ok(serde_json::from_reader(std::fs::File::open(path)?)?),
+        #[cfg(feature = "toml")]
+        Some("toml") => Ok(toml::from_str::<TomlCountries>(&std::fs::read_to_string(path)?)?.countries),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => Ok(serde_yaml::from_reader(std::fs::File::open(path)?)?),
+        #[cfg(not(feature = "toml"))]
+        Some("toml") => {
+            bail!("unable to read '{}'; toml country data requires the 'toml' feature", path.to_string_lossy())
+        }
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml" | "yml") => {
+            bail!("unable to read '{}'; yaml country data requires the 'yaml' feature", path.to_string_lossy())
+        }
+        Some(extension) => {
+            bail!("unsupported country data format '.{extension}'; expected '.json', '.toml', '.yaml', or '.yml'")
         }
     }
 }
@@ -1,23 +1,96 @@
+use std::cmp::{Ordering, Reverse};
 use std::fmt::Display;
-use std::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::ControlFlow;
 use std::path::Path;
+use std::rc::Rc;
 use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use csv::{ByteRecord, Position, Trim};
 use geolocate_core::country::{Country, CountryCode};
 use geolocate_core::ip::{Address, IpAddrBlock, IpAddrBlockMap};
-use geolocate_core::prelude::{Ipv4AddrBlock, Ipv4AddrBlockMap, Ipv6AddrBlock, Ipv6AddrBlockMap};
+use geolocate_core::prelude::{
+    Asn, AsnBlock, AsnBlockMap, Ipv4AddrBlock, Ipv4AddrBlockMap, Ipv6AddrBlock, Ipv6AddrBlockMap, Ipv6AddrPrefix,
+};
+#[cfg(feature = "progress")]
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use serde::de::value::{Error as DeError, StrDeserializer};
 use serde::Deserialize;
 
+use crate::error::CliError;
+
+/// The number of entries to allocate for a parsed map if no capacity is given and the file's size is unknown.
+const DEFAULT_CAPACITY: usize = 256;
+/// The approximate average length in bytes of a record within an IPv4 map file, used to estimate its entry count.
+const IPV4_RECORD_LENGTH: u64 = 24;
+/// The approximate average length in bytes of a record within an IPv6 map file, used to estimate its entry count.
+const IPV6_RECORD_LENGTH: u64 = 48;
+/// The approximate average length in bytes of a record within an autonomous system number map file, used to estimate
+/// its entry count.
+const ASN_RECORD_LENGTH: u64 = 24;
+
 /// A country that could potentially be unresolved.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum MaybeCountry {
     /// The country is present.
-    Present(Country),
+    ///
+    /// Countries are shared between every block that they are assigned to, rather than being cloned for each block.
+    Present(Rc<Country>),
     /// The country is missing.
     Missing(CountryCode),
 }
 
+impl MaybeCountry {
+    /// Returns the country's code.
+    #[inline]
+    #[must_use]
+    pub fn code(&self) -> CountryCode {
+        match self {
+            Self::Present(country) => country.code,
+            Self::Missing(code) => *code,
+        }
+    }
+
+    /// Returns the country, or [`None`] if it is missing.
+    #[inline]
+    #[must_use]
+    pub fn country(&self) -> Option<&Country> {
+        match self {
+            Self::Present(country) => Some(country),
+            Self::Missing(_) => None,
+        }
+    }
+
+    /// Compares two countries in the given order.
+    ///
+    /// Missing countries are named by their codes when compared by name.
+    #[must_use]
+    pub fn cmp_by(&self, other: &Self, order: SortOrder) -> Ordering {
+        match (order, self, other) {
+            (SortOrder::Code, ..) => self.code().cmp(&other.code()),
+            (SortOrder::Name, Self::Present(a), Self::Present(b)) => a.cmp_by_name(b),
+            (SortOrder::Name, ..) => {
+                self.to_string().cmp(&other.to_string()).then_with(|| self.code().cmp(&other.code()))
+            }
+        }
+    }
+}
+
+/// The orders that countries may be sorted in.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// Sorted by alpha-2 code.
+    #[default]
+    Code,
+    /// Sorted alphabetically by name.
+    Name,
+}
+
 impl Display for MaybeCountry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -27,6 +100,63 @@ impl Display for MaybeCountry {
     }
 }
 
+/// Options that control how IP map files are parsed.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// The byte that separates each field within a record.
+    pub delimiter: u8,
+    /// Whether the first record of the file is a header row.
+    pub has_headers: bool,
+    /// The byte that marks a line as a comment, if any.
+    pub comment: Option<u8>,
+    /// Whether each record stores its block within a single hyphenated range column, rather than two columns.
+    pub range_column: bool,
+    /// Whether the file is an RIR delegated-stats file, ignoring the delimiter, header, and range column options.
+    pub delegated: bool,
+    /// Whether to display the file's reading progress on the standard error, if it is a terminal.
+    ///
+    /// This does nothing unless the `progress` feature is enabled.
+    pub progress: bool,
+    /// Whether to fail if any record's country code could not be parsed or resolved, rather than keeping it as a
+    /// [`MaybeCountry::Missing`] country.
+    pub strict: bool,
+    /// Whether records whose country code is prefixed with `!` mark exclusions, whose blocks are removed from the
+    /// blocks of the same country once every record has been read.
+    pub exclusions: bool,
+}
+
+impl Default for ParseOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: false,
+            comment: Some(b'#'),
+            range_column: false,
+            delegated: false,
+            progress: false,
+            strict: false,
+            exclusions: false,
+        }
+    }
+}
+
+/// Parses a field delimiter from the given string.
+///
+/// This accepts any single ASCII character, as well as the escape sequence `\t` and the name `tab`.
+///
+/// # Errors
+///
+/// This function will return an error if the string does not represent a single ASCII character.
+pub fn parse_delimiter(value: &str) -> Result<u8> {
+    match value {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => bail!("invalid delimiter '{value}'; expected a single ascii character"),
+    }
+}
+
 /// The format to use when deserializing an IPv4 map file's entry.
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
 pub struct Ipv4Schema {
@@ -53,76 +183,744 @@ pub struct Ipv6Schema {
     pub country: Box<str>,
 }
 
+/// The format to use when deserializing an IPv4 map file's entry that stores its block as a single range.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
+pub struct Ipv4RangeSchema {
+    /// The address range, formatted as `start-end`.
+    pub range: Box<str>,
+    /// A country's Alpha-2 code.
+    pub country: Box<str>,
+}
+
+/// The format to use when deserializing an IPv6 map file's entry that stores its block as a single range.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
+pub struct Ipv6RangeSchema {
+    /// The address range, formatted as `start-end`.
+    pub range: Box<str>,
+    /// A country's Alpha-2 code.
+    pub country: Box<str>,
+}
+
+/// The format to use when deserializing an autonomous system number map file's entry.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
+pub struct AsnSchema {
+    /// The starting number.
+    #[serde(deserialize_with = "crate::ip::deserialize_asn")]
+    pub start: Asn,
+    /// The ending number.
+    #[serde(deserialize_with = "crate::ip::deserialize_asn")]
+    pub end: Asn,
+    /// A country's Alpha-2 code.
+    pub country: Box<str>,
+}
+
+/// The format to use when deserializing an autonomous system number map file's entry that stores its block as a
+/// single range.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Deserialize)]
+pub struct AsnRangeSchema {
+    /// The number range, formatted as `start-end`.
+    pub range: Box<str>,
+    /// A country's Alpha-2 code.
+    pub country: Box<str>,
+}
+
 /// Attempts to parse an IPv4 map file.
 ///
 /// # Errors
 ///
-/// This function will return an error if the file could not be parsed.
+/// This function will return an error if the file could not be found or parsed.
+#[inline]
+pub fn parse_ipv4_map_file<P, F>(
+    path: P,
+    capacity: Option<usize>,
+    options: ParseOptions,
+    resolve: F,
+) -> Result<Ipv4AddrBlockMap<MaybeCountry>, CliError>
+where
+    P: AsRef<Path>,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+{
+    let path = path.as_ref();
+
+    self::parse_map(
+        path,
+        capacity,
+        options,
+        resolve,
+        |visit| self::visit_ipv4_map_file(path, options, visit),
+        IPV4_RECORD_LENGTH,
+    )
+}
+
+/// Attempts to parse an IPv6 map file.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be found or parsed.
 #[inline]
-pub fn parse_ipv4_map_file<P, F>(path: P, capacity: Option<usize>, resolve: F) -> Result<Ipv4AddrBlockMap<MaybeCountry>>
+pub fn parse_ipv6_map_file<P, F>(
+    path: P,
+    capacity: Option<usize>,
+    options: ParseOptions,
+    resolve: F,
+) -> Result<Ipv6AddrBlockMap<MaybeCountry>, CliError>
+where
+    P: AsRef<Path>,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+{
+    let path = path.as_ref();
+
+    self::parse_map(
+        path,
+        capacity,
+        options,
+        resolve,
+        |visit| self::visit_ipv6_map_file(path, options, visit),
+        IPV6_RECORD_LENGTH,
+    )
+}
+
+/// Attempts to parse an autonomous system number map file.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be found or parsed.
+#[inline]
+pub fn parse_asn_map_file<P, F>(
+    path: P,
+    capacity: Option<usize>,
+    options: ParseOptions,
+    resolve: F,
+) -> Result<AsnBlockMap<MaybeCountry>, CliError>
+where
+    P: AsRef<Path>,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+{
+    let path = path.as_ref();
+
+    self::parse_map(
+        path,
+        capacity,
+        options,
+        resolve,
+        |visit| self::visit_asn_map_file(path, options, visit),
+        ASN_RECORD_LENGTH,
+    )
+}
+
+/// Attempts to parse a map file whose records are read using the given visiting function.
+///
+/// If no capacity is given, it is estimated from the file's size and the given average record length.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be found or parsed.
+fn parse_map<A, F, W>(
+    path: &Path,
+    capacity: Option<usize>,
+    options: ParseOptions,
+    resolve: F,
+    visit_file: W,
+    record_length: u64,
+) -> Result<IpAddrBlockMap<A, MaybeCountry>, CliError>
+where
+    A: Address,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+    W: FnOnce(&mut dyn FnMut(IpAddrBlock<A>, CountryCode, bool, &Position) -> ControlFlow<()>) -> Result<()>,
+{
+    let capacity = capacity.unwrap_or_else(|| self::estimate_capacity(path, record_length));
+    let mut map = IpAddrBlockMap::with_capacity(capacity);
+
+    let mut unresolved = None;
+    let mut exclusions = Vec::new();
+
+    visit_file(&mut |block, code, excluded, position| {
+        let country = self::maybe_country(code, &resolve);
+
+        if options.strict && matches!(country, MaybeCountry::Missing(_)) {
+            unresolved = Some((code, position.line()));
+
+            return ControlFlow::Break(());
+        }
+
+        if excluded {
+            exclusions.push((block, country));
+        } else {
+            map.insert_unstable(block, country);
+        }
+
+        ControlFlow::Continue(())
+    })
+    .and_then(|()| self::check_unresolved(unresolved))
+    .map_err(|error| CliError::parse_failed(path, error))?;
+
+    map.normalize();
+    self::apply_exclusions(&mut map, exclusions);
+
+    Ok(map)
+}
+
+/// Removes each excluded block from the blocks of the map that are assigned the same country.
+///
+/// Exclusions are applied after every record has been read, so they may appear anywhere within the file.
+fn apply_exclusions<A: Address>(
+    map: &mut IpAddrBlockMap<A, MaybeCountry>,
+    exclusions: Vec<(IpAddrBlock<A>, MaybeCountry)>,
+) {
+    for (block, country) in exclusions {
+        map.subtract_if(block, |_, value| *value == country);
+    }
+}
+
+/// Resolves the given address by reading the map file at the given path record-by-record.
+///
+/// This returns the country of the most specific block that contains the address, alongside the position of the record
+/// that the block was read from. Overlapping blocks are resolved using the same rule as a parsed map, as described by
+/// [`IpAddrBlockMap`], so this always agrees with [`parse_ipv4_map_file`] and [`parse_ipv6_map_file`].
+///
+/// Unlike those functions, this never builds the full map, only keeping the records whose blocks contain the address
+/// and any exclusions. Since a more specific block may appear anywhere, the entire file is always read. The file must
+/// contain source data of the same IP version as the address.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be parsed.
+pub fn resolve_streaming<P, F>(
+    path: P,
+    options: ParseOptions,
+    resolve: F,
+    address: IpAddr,
+) -> Result<Option<(MaybeCountry, Position)>, CliError>
+where
+    P: AsRef<Path>,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+{
+    let path = path.as_ref();
+    let found = match address {
+        IpAddr::V4(ip) => self::find_streaming(ip, &resolve, |visit| self::visit_ipv4_map_file(path, options, visit))
+            .map(self::most_specific),
+        IpAddr::V6(ip) => self::find_streaming(ip, &resolve, |visit| self::visit_ipv6_map_file(path, options, visit))
+            .map(self::most_specific),
+    };
+
+    found.map_err(|error| CliError::parse_failed(path, error))
+}
+
+/// Returns the country and record position of the most specific of the given blocks.
+///
+/// Of several equally specific blocks, the one read first is returned, matching the duplicate handling of a parsed map.
+fn most_specific<A: Address>(
+    candidates: Vec<(IpAddrBlock<A>, MaybeCountry, Position)>,
+) -> Option<(MaybeCountry, Position)> {
+    candidates.into_iter().min_by_key(|(block, ..)| (Reverse(block.start()), block.end())).map(|(_, c, p)| (c, p))
+}
+
+/// Finds the record within the map file at the given path that the given matched block was parsed from, returning its
+/// position.
+///
+/// The matched block is given by its first and last addresses, as found within a parsed map by resolving the given
+/// address. If exclusions split a record's block, the matched block is the portion of it that remains around the
+/// address. Of several records with the same block, the one read first is returned, matching the duplicate handling of
+/// a parsed map.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be parsed.
+pub fn find_source_record<P, F>(
+    path: P,
+    options: ParseOptions,
+    resolve: F,
+    address: IpAddr,
+    matched: (IpAddr, IpAddr),
+) -> Result<Option<Position>, CliError>
+where
+    P: AsRef<Path>,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+{
+    let path = path.as_ref();
+    let found = match address {
+        IpAddr::V4(ip) => self::find_streaming(ip, &resolve, |visit| self::visit_ipv4_map_file(path, options, visit))
+            .map(|candidates| self::position_of(candidates, matched)),
+        IpAddr::V6(ip) => self::find_streaming(ip, &resolve, |visit| self::visit_ipv6_map_file(path, options, visit))
+            .map(|candidates| self::position_of(candidates, matched)),
+    };
+
+    found.map_err(|error| CliError::parse_failed(path, error))
+}
+
+/// Returns the record position of the first of the given blocks whose first and last addresses are those given.
+fn position_of<A: Address + Into<IpAddr>>(
+    candidates: Vec<(IpAddrBlock<A>, MaybeCountry, Position)>,
+    (start, end): (IpAddr, IpAddr),
+) -> Option<Position> {
+    candidates
+        .into_iter()
+        .find(|(block, ..)| block.start().into() == start && block.end().into() == end)
+        .map(|(.., p)| p)
+}
+
+/// Returns each block containing the given address within the records passed to the visitor given to `visit_file`,
+/// in the order that they were read, alongside its country and its record's position.
+///
+/// Each returned block is the portion that remains after removing any exclusions, and records whose blocks no longer
+/// contain the address are skipped.
+///
+/// # Errors
+///
+/// This function will return an error if `visit_file` fails.
+#[allow(clippy::type_complexity)]
+fn find_streaming<A, F, W>(
+    address: A,
+    resolve: F,
+    visit_file: W,
+) -> Result<Vec<(IpAddrBlock<A>, MaybeCountry, Position)>>
+where
+    A: Address,
+    F: Fn(CountryCode) -> Option<Rc<Country>>,
+    W: FnOnce(&mut dyn FnMut(IpAddrBlock<A>, CountryCode, bool, &Position) -> ControlFlow<()>) -> Result<()>,
+{
+    let mut candidates = Vec::new();
+    let mut exclusions = Vec::new();
+
+    visit_file(&mut |block, code, exclusion, position| {
+        if exclusion {
+            exclusions.push((block, code));
+        } else if block.range().contains(&address) {
+            candidates.push((block, code, position.clone()));
+        }
+
+        ControlFlow::Continue(())
+    })?;
+
+    let exclusions: Vec<_> =
+        exclusions.into_iter().map(|(block, code)| (block, self::maybe_country(code, &resolve))).collect();
+
+    Ok(candidates
+        .into_iter()
+        .filter_map(|(block, code, position)| {
+            let country = self::maybe_country(code, &resolve);
+            let excluded = exclusions.iter().filter(|(_, c)| *c == country).map(|(b, _)| b);
+
+            Some((self::remaining_block(block, address, excluded)?, country, position))
+        })
+        .collect())
+}
+
+/// Returns the portion of the given block that contains the given address after removing each of the given excluded
+/// blocks, or [`None`] if the address itself is excluded.
+fn remaining_block<'e, A: Address + 'e>(
+    block: IpAddrBlock<A>,
+    address: A,
+    mut excluded: impl Iterator<Item = &'e IpAddrBlock<A>>,
+) -> Option<IpAddrBlock<A>> {
+    excluded.try_fold(block, |piece, excluded| {
+        let pieces: [_; 2] = piece.difference(excluded).into();
+
+        pieces.into_iter().flatten().find(|b| b.range().contains(&address))
+    })
+}
+
+/// Reads the record at the given position within the map file at the given path, as returned by [`resolve_streaming`]
+/// or [`find_source_record`], returning its line number and raw text.
+///
+/// The record is read using the same delimiter as when parsing, so a record whose quoted fields span several lines is
+/// returned in full.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be read.
+pub fn read_source_record<P: AsRef<Path>>(
+    path: P,
+    options: ParseOptions,
+    position: &Position,
+) -> Result<(u64, Box<str>), CliError> {
+    let path = path.as_ref();
+    let read = || -> Result<(u64, Box<str>)> {
+        let mut file = std::fs::File::open(path)?;
+
+        file.seek(SeekFrom::Start(position.byte()))?;
+
+        // The reader's position after reading the record is its length in bytes, including its terminator.
+        let length = {
+            let mut reader = csv::ReaderBuilder::new()
+                .delimiter(if options.delegated { b'|' } else { options.delimiter })
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(&mut file);
+
+            reader.read_byte_record(&mut ByteRecord::new())?;
+            reader.position().byte()
+        };
+        let mut bytes = Vec::with_capacity(usize::try_from(length)?);
+
+        file.seek(SeekFrom::Start(position.byte()))?;
+        file.take(length).read_to_end(&mut bytes)?;
+
+        // The first record of a file may be preceded by a byte order mark, which the reader skips when parsing.
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_start_matches('\u{feff}');
+
+        // The reader positions a record that follows a `\r\n` terminator at its `\n`, before the record's own line.
+        let (skipped, text) = text.strip_prefix('\n').map_or((0, text), |text| (1, text));
+
+        Ok((position.line() + skipped, text.trim_end_matches(['\r', '\n']).into()))
+    };
+
+    read().map_err(|error| CliError::parse_failed(path, error))
+}
+
+/// Estimates the number of records within the file at the given path from its size and the given average record
+/// length.
+///
+/// If the file's size could not be read, this returns [`DEFAULT_CAPACITY`].
+fn estimate_capacity(path: &Path, record_length: u64) -> usize {
+    std::fs::metadata(path).map_or(DEFAULT_CAPACITY, |metadata| {
+        usize::try_from(metadata.len() / record_length).unwrap_or(DEFAULT_CAPACITY)
+    })
+}
+
+/// Returns an error describing the given unresolved country code and the line that it was read from, if any.
+///
+/// # Errors
+///
+/// This function will return an error if an unresolved country code is given.
+fn check_unresolved(unresolved: Option<(CountryCode, u64)>) -> Result<()> {
+    match unresolved {
+        Some((code, line)) => bail!("unresolved country code '{code}' on line {line}"),
+        None => Ok(()),
+    }
+}
+
+/// Resolves the given country code into a [`MaybeCountry`].
+fn maybe_country<F: Fn(CountryCode) -> Option<Rc<Country>>>(code: CountryCode, resolve: F) -> MaybeCountry {
+    resolve(code).map_or(MaybeCountry::Missing(code), MaybeCountry::Present)
+}
+
+/// Reads each entry of an IPv4 map file, passing it to `visit` until it breaks.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be parsed.
+fn visit_ipv4_map_file<P, V>(path: P, options: ParseOptions, visit: V) -> Result<()>
 where
     P: AsRef<Path>,
-    F: Fn(CountryCode) -> Option<Country>,
+    V: FnMut(Ipv4AddrBlock, CountryCode, bool, &Position) -> ControlFlow<()>,
 {
-    self::parse_ip_map(path, capacity, resolve, |Ipv4Schema { start, end, country }| {
+    if options.delegated {
+        return self::visit_delegated_map(path, options, "ipv4", visit, |start, value| {
+            let start = Ipv4Addr::from_str(start)?;
+            let count = value.parse::<u32>()?;
+            let Some(end) = count.checked_sub(1).and_then(|offset| start.to_bits().checked_add(offset)) else {
+                bail!("invalid address count '{value}' for start address {start}");
+            };
+
+            Ok(Ipv4AddrBlock::try_new(start, Ipv4Addr::from_bits(end))?)
+        });
+    }
+    if options.range_column {
+        return self::visit_ip_map(path, options, visit, |Ipv4RangeSchema { range, country }| {
+            let (start, end) = self::split_range(&range, |d| crate::ip::deserialize_ipv4(d))?;
+            let block = Ipv4AddrBlock::try_new(start, end)?;
+            let (code, exclusion) = self::parse_code(&country, options)?;
+
+            Ok((block, code, exclusion))
+        });
+    }
+
+    self::visit_ip_map(path, options, visit, |Ipv4Schema { start, end, country }| {
         let block = Ipv4AddrBlock::try_new(start, end)?;
-        let code = CountryCode::from_str(&country)?;
+        let (code, exclusion) = self::parse_code(&country, options)?;
 
-        Ok((block, code))
+        Ok((block, code, exclusion))
     })
 }
 
-/// Attempts to parse an IPv6 map file.
+/// Reads each entry of an IPv6 map file, passing it to `visit` until it breaks.
 ///
 /// # Errors
 ///
 /// This function will return an error if the file could not be parsed.
-#[inline]
-pub fn parse_ipv6_map_file<P, F>(path: P, capacity: Option<usize>, resolve: F) -> Result<Ipv6AddrBlockMap<MaybeCountry>>
+fn visit_ipv6_map_file<P, V>(path: P, options: ParseOptions, visit: V) -> Result<()>
 where
     P: AsRef<Path>,
-    F: Fn(CountryCode) -> Option<Country>,
+    V: FnMut(Ipv6AddrBlock, CountryCode, bool, &Position) -> ControlFlow<()>,
 {
-    self::parse_ip_map(path, capacity, resolve, |Ipv6Schema { start, end, country }| {
+    if options.delegated {
+        return self::visit_delegated_map(path, options, "ipv6", visit, |start, value| {
+            let start = Ipv6Addr::from_str(start)?;
+            let Some(prefix) = value.parse().ok().and_then(|length| Ipv6AddrPrefix::new(start, length)) else {
+                bail!("invalid prefix length '{value}' for network address {start}");
+            };
+
+            Ok(prefix.block())
+        });
+    }
+    if options.range_column {
+        return self::visit_ip_map(path, options, visit, |Ipv6RangeSchema { range, country }| {
+            let (start, end) = self::split_range(&range, |d| crate::ip::deserialize_ipv6(d))?;
+            let block = Ipv6AddrBlock::try_new(start, end)?;
+            let (code, exclusion) = self::parse_code(&country, options)?;
+
+            Ok((block, code, exclusion))
+        });
+    }
+
+    self::visit_ip_map(path, options, visit, |Ipv6Schema { start, end, country }| {
         let block = Ipv6AddrBlock::try_new(start, end)?;
-        let code = CountryCode::from_str(&country)?;
+        let (code, exclusion) = self::parse_code(&country, options)?;
 
-        Ok((block, code))
+        Ok((block, code, exclusion))
     })
 }
 
-/// Attempts to parse an IP map file.
+/// Reads each entry of an autonomous system number map file, passing it to `visit` until it breaks.
 ///
 /// # Errors
 ///
 /// This function will return an error if the file could not be parsed.
-pub fn parse_ip_map<A, P, R, F, T>(
-    path: P,
-    capacity: Option<usize>,
-    resolve: R,
-    compute: F,
-) -> Result<IpAddrBlockMap<A, MaybeCountry>>
+fn visit_asn_map_file<P, V>(path: P, options: ParseOptions, visit: V) -> Result<()>
+where
+    P: AsRef<Path>,
+    V: FnMut(AsnBlock, CountryCode, bool, &Position) -> ControlFlow<()>,
+{
+    if options.delegated {
+        return self::visit_delegated_map(path, options, "asn", visit, |start, value| {
+            let start = geolocate_core::asn::parse_asn(start)?;
+            let count = value.parse::<Asn>()?;
+            let Some(end) = count.checked_sub(1).and_then(|offset| start.checked_add(offset)) else {
+                bail!("invalid number count '{value}' for start number {start}");
+            };
+
+            Ok(AsnBlock::try_new(start, end)?)
+        });
+    }
+    if options.range_column {
+        return self::visit_ip_map(path, options, visit, |AsnRangeSchema { range, country }| {
+            let (start, end) = self::split_range(&range, |d| crate::ip::deserialize_asn(d))?;
+            let block = AsnBlock::try_new(start, end)?;
+            let (code, exclusion) = self::parse_code(&country, options)?;
+
+            Ok((block, code, exclusion))
+        });
+    }
+
+    self::visit_ip_map(path, options, visit, |AsnSchema { start, end, country }| {
+        let block = AsnBlock::try_new(start, end)?;
+        let (code, exclusion) = self::parse_code(&country, options)?;
+
+        Ok((block, code, exclusion))
+    })
+}
+
+/// Parses a record's country code, returning whether it marks an exclusion.
+///
+/// Codes are only treated as exclusions if they are prefixed with `!` and exclusions are enabled by the given options.
+///
+/// # Errors
+///
+/// This function will return an error if the country code could not be parsed.
+fn parse_code(country: &str, options: ParseOptions) -> Result<(CountryCode, bool)> {
+    match country.strip_prefix('!') {
+        Some(country) if options.exclusions => Ok((CountryCode::from_str(country)?, true)),
+        _ => Ok((CountryCode::from_str(country)?, false)),
+    }
+}
+
+/// Splits a hyphenated address range into its start and end addresses, deserializing each using the given function.
+///
+/// # Errors
+///
+/// This function will return an error if the range is not hyphenated or either address could not be deserialized.
+fn split_range<A, F>(range: &str, deserialize: F) -> Result<(A, A)>
+where
+    F: Fn(StrDeserializer<'_, DeError>) -> Result<A, DeError>,
+{
+    let Some((start, end)) = range.split_once('-') else {
+        bail!("invalid address range '{range}'; expected 'start-end'");
+    };
+
+    let start = deserialize(StrDeserializer::new(start.trim()))?;
+    let end = deserialize(StrDeserializer::new(end.trim()))?;
+
+    Ok((start, end))
+}
+
+/// Opens the map file at the given path for reading.
+///
+/// If the `progress` feature is enabled and requested by the given options, the file is wrapped in a reader that
+/// displays the number of bytes read against the file's size, as long as the standard error is a terminal.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be opened.
+#[cfg_attr(not(feature = "progress"), allow(unused_variables))]
+fn open_map_file(path: &Path, options: ParseOptions) -> Result<Box<dyn Read>> {
+    let file = std::fs::File::open(path)?;
+
+    #[cfg(feature = "progress")]
+    if options.progress && std::io::stderr().is_terminal() {
+        // These are indicatif's template keys rather than formatting arguments.
+        #[allow(clippy::literal_string_with_formatting_args)]
+        let style = ProgressStyle::with_template("{msg} [{bar:40}] {bytes}/{total_bytes} ({eta})")?;
+        let bar = ProgressBar::new(file.metadata()?.len())
+            .with_style(style.progress_chars("=> "))
+            .with_message(path.to_string_lossy().into_owned())
+            .with_finish(ProgressFinish::AndClear);
+
+        return Ok(Box::new(bar.wrap_read(file)));
+    }
+
+    Ok(Box::new(file))
+}
+
+/// Reads each entry of an IP map file, passing it to `visit` until it breaks.
+///
+/// A leading UTF-8 byte order mark is skipped, and whitespace surrounding each field, including a stray `\r` left by
+/// mismatched line endings, is trimmed before the record is deserialized.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be parsed.
+pub fn visit_ip_map<A, P, V, F, T>(path: P, options: ParseOptions, mut visit: V, compute: F) -> Result<()>
 where
     A: Address + for<'de> Deserialize<'de>,
     P: AsRef<Path>,
-    R: Fn(CountryCode) -> Option<Country>,
-    F: Fn(T) -> Result<(IpAddrBlock<A>, CountryCode)>,
+    V: FnMut(IpAddrBlock<A>, CountryCode, bool, &Position) -> ControlFlow<()>,
+    F: Fn(T) -> Result<(IpAddrBlock<A>, CountryCode, bool)>,
     T: for<'de> Deserialize<'de>,
 {
-    const DEFAULT_CAPACITY: usize = 256;
+    let file = self::open_map_file(path.as_ref(), options)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.has_headers)
+        .comment(options.comment)
+        .trim(Trim::All)
+        .from_reader(file);
+    let headers = if options.has_headers { Some(reader.headers()?.clone()) } else { None };
 
-    let file = std::fs::File::open(path)?;
-    let reader = csv::ReaderBuilder::new().has_headers(false).comment(Some(b'#')).from_reader(file);
-    let mut map = IpAddrBlockMap::with_capacity(capacity.unwrap_or(DEFAULT_CAPACITY));
+    for record in reader.records() {
+        let record = record?;
+        let position = record.position().cloned().unwrap_or_else(Position::new);
+        let line = position.line();
+        let (block, code, exclusion) = record
+            .deserialize(headers.as_ref())
+            .map_err(anyhow::Error::from)
+            .and_then(&compute)
+            .with_context(|| format!("invalid entry on line {line}"))?;
+
+        if visit(block, code, exclusion, &position).is_break() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads each entry of an RIR delegated-stats file, such as `delegated-ripencc-latest`, passing it to `visit` until it
+/// breaks.
+///
+/// Each record is formatted as `registry|cc|type|start|value|date|status`. Only records of the given type are read,
+/// with their start address and value being passed to `compute`; the file's version line, summary lines, and records
+/// without a country code are skipped.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be parsed.
+pub fn visit_delegated_map<A, P, V, F>(
+    path: P,
+    options: ParseOptions,
+    kind: &str,
+    mut visit: V,
+    compute: F,
+) -> Result<()>
+where
+    A: Address,
+    P: AsRef<Path>,
+    V: FnMut(IpAddrBlock<A>, CountryCode, bool, &Position) -> ControlFlow<()>,
+    F: Fn(&str, &str) -> Result<IpAddrBlock<A>>,
+{
+    let file = self::open_map_file(path.as_ref(), options)?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'|')
+        .has_headers(false)
+        .comment(options.comment)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(file);
+
+    for record in reader.records() {
+        let record = record?;
+        let position = record.position().cloned().unwrap_or_else(Position::new);
+        let line = position.line();
+
+        // The version line has fewer fields, and summary lines use a wildcard in place of the country code.
+        let (Some(code), Some(record_kind), Some(start), Some(value)) =
+            (record.get(1), record.get(2), record.get(3), record.get(4))
+        else {
+            continue;
+        };
+
+        if record_kind != kind || code.is_empty() || code == "*" {
+            continue;
+        }
 
-    for entry in reader.into_deserialize() {
-        let (block, code) = compute(entry?)?;
-        let country = resolve(code).map_or(MaybeCountry::Missing(code), MaybeCountry::Present);
+        let (block, code) = compute(start, value)
+            .and_then(|block| Ok((block, CountryCode::from_str(code)?)))
+            .with_context(|| format!("invalid entry on line {line}"))?;
 
-        map.insert_unstable(block, country);
+        if visit(block, code, false, &position).is_break() {
+            break;
+        }
     }
 
-    map.normalize();
+    Ok(())
+}
 
-    Ok(map)
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::ops::ControlFlow;
+    use std::str::FromStr;
+
+    use geolocate_core::country::CountryCode;
+
+    use super::ParseOptions;
+
+    #[test]
+    fn visit_reads_bom_and_crlf_records() {
+        let path = std::env::temp_dir().join(format!("geolocate-bom-{}.csv", std::process::id()));
+
+        std::fs::write(&path, "\u{feff}16777216,16777471,AU\r\n16777472,16777727,CN\r\n").unwrap();
+
+        let mut records = Vec::new();
+
+        super::visit_ipv4_map_file(&path, ParseOptions::default(), |block, code, exclusion, position| {
+            records.push((block.start(), block.end(), code, exclusion, position.clone()));
+
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+
+        let (start, end, code, exclusion, position) = &records[0];
+
+        assert_eq!((*start, *end), (Ipv4Addr::new(1, 0, 0, 0), Ipv4Addr::new(1, 0, 0, 255)));
+        assert_eq!(*code, CountryCode::from_str("AU").unwrap());
+        assert!(!exclusion);
+
+        let (line, text) = super::read_source_record(&path, ParseOptions::default(), position).unwrap();
+
+        assert_eq!((line, &*text), (1, "16777216,16777471,AU"));
+
+        let (.., code, _, position) = &records[1];
+
+        assert_eq!(*code, CountryCode::from_str("CN").unwrap());
+
+        let (line, text) = super::read_source_record(&path, ParseOptions::default(), position).unwrap();
+
+        assert_eq!((line, &*text), (2, "16777472,16777727,CN"));
+
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+
+/// The largest number of decimal places that a percentage may be formatted with.
+pub const MAX_PRECISION: u8 = 18;
+
+/// The threshold above which both sides of a ratio are halved, so that its long division cannot overflow.
+const RATIO_LIMIT: u128 = 1 << 120;
+
+/// Formats the given integer with a comma between each group of three digits, such as `1,234,567`.
+#[must_use]
+pub fn grouped(value: impl Into<u128>) -> String {
+    let digits = value.into().to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 {
+            output.push(',');
+        }
+
+        output.push(digit);
+    }
+
+    output
+}
+
+/// Formats the given count as a percentage of the given total, rounded to the given number of decimal places.
+///
+/// This uses integer arithmetic, so that large counts, such as IPv6 address counts, do not lose precision to floats.
+/// The precision is clamped to [`MAX_PRECISION`].
+#[must_use]
+pub fn percentage(count: impl Into<u128>, total: impl Into<u128>, precision: u8) -> String {
+    let (mut count, mut total) = (count.into(), total.into());
+    let precision = precision.min(MAX_PRECISION);
+
+    if total == 0 {
+        return format!("{:.*}%", usize::from(precision), 0);
+    }
+
+    // Any precision lost by halving both sides is far smaller than the largest displayable precision.
+    while total >= RATIO_LIMIT || count >= RATIO_LIMIT {
+        count >>= 1;
+        total >>= 1;
+    }
+
+    let total = total.max(1);
+    let mut value = count * 100 / total;
+    let mut remainder = count * 100 % total;
+
+    for _ in 0 .. precision {
+        remainder *= 10;
+        value = value.saturating_mul(10).saturating_add(remainder / total);
+        remainder %= total;
+    }
+
+    if remainder * 2 >= total {
+        value += 1;
+    }
+
+    let scale = 10_u128.pow(u32::from(precision));
+
+    if precision == 0 {
+        format!("{}%", self::grouped(value))
+    } else {
+        format!("{}.{:0width$}%", self::grouped(value / scale), value % scale, width = usize::from(precision))
+    }
+}
+
+/// Parses a number of decimal places, which may not exceed [`MAX_PRECISION`].
+///
+/// # Errors
+///
+/// This function will return an error if the value is not an integer within range.
+pub fn parse_precision(value: &str) -> Result<u8> {
+    match value.parse::<u8>() {
+        Ok(precision) if precision <= MAX_PRECISION => Ok(precision),
+        _ => bail!("invalid precision '{value}'; expected a number of decimal places from 0 to {MAX_PRECISION}"),
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::{BufWriter, StdoutLock, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use clap::Args;
+#[cfg(feature = "table")]
+use comfy_table::{CellAlignment, ContentArrangement, Table};
+
+use crate::error::Result;
+
+/// The arguments shared by every command that writes its output.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Args)]
+pub struct OutputArguments {
+    /// Write the output to this file rather than the standard output, creating its parent directories as needed.
+    #[arg(long = "output")]
+    pub output: Option<Box<Path>>,
+}
+
+impl OutputArguments {
+    /// Opens the requested output.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the output file or its parent directories could not be created.
+    pub fn open(&self) -> Result<Output> {
+        let Some(path) = self.output.as_deref() else {
+            return Ok(Output::Stdout(std::io::stdout().lock()));
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("unable to create directory '{}'", parent.to_string_lossy()))?;
+        }
+
+        let file = File::create(path).with_context(|| format!("unable to create file '{}'", path.to_string_lossy()))?;
+
+        Ok(Output::File(BufWriter::new(file)))
+    }
+}
+
+/// The destination that a command writes its output to.
+#[derive(Debug)]
+pub enum Output {
+    /// The standard output.
+    Stdout(StdoutLock<'static>),
+    /// A buffered file.
+    File(BufWriter<File>),
+}
+
+impl Write for Output {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::File(file) => file.write(buf),
+        }
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.write_all(buf),
+            Self::File(file) => file.write_all(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+/// Writes `#`-prefixed comment lines recording the tool's version, the current time, and the given source files.
+///
+/// The IP source data parser skips these lines, so they may precede generated IP source data.
+///
+/// # Errors
+///
+/// This function will return an error if the lines could not be written.
+pub fn write_provenance(out: &mut impl Write, sources: &[&Path]) -> std::io::Result<()> {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+
+    writeln!(out, "# Generated by {} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+    writeln!(out, "# Generated at {}", self::timestamp_display(seconds))?;
+
+    for source in sources {
+        writeln!(out, "# Source: {}", source.to_string_lossy())?;
+    }
+
+    Ok(())
+}
+
+/// Writes the given rows as a table with box-drawn borders, beneath a header row of the given column names.
+///
+/// Columns whose indices are listed within `numeric` are right-aligned. Tables that are too wide for the terminal have
+/// their cells wrapped to fit.
+///
+/// # Errors
+///
+/// This function will return an error if the table could not be written.
+#[cfg(feature = "table")]
+pub fn write_table<R>(
+    out: &mut impl Write,
+    header: &[&str],
+    numeric: &[usize],
+    rows: impl IntoIterator<Item = R>,
+) -> std::io::Result<()>
+where
+    R: IntoIterator<Item = String>,
+{
+    let mut table = Table::new();
+
+    table.load_preset(comfy_table::presets::UTF8_FULL).set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(header);
+
+    for row in rows {
+        table.add_row(row);
+    }
+
+    for index in numeric {
+        if let Some(column) = table.column_mut(*index) {
+            column.set_cell_alignment(CellAlignment::Right);
+        }
+    }
+
+    writeln!(out, "{table}")
+}
+
+/// Returns the given number of seconds since the Unix epoch formatted as an RFC 3339 UTC timestamp.
+#[must_use]
+pub fn timestamp_display(seconds: u64) -> String {
+    let (days, time) = (seconds / 86_400, seconds % 86_400);
+    // Converts the number of days into a civil date, using eras of 400 years that begin on the 1st of March.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", time / 3_600, time % 3_600 / 60, time % 60)
+}
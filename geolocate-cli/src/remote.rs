@@ -0,0 +1,60 @@
+use std::ffi::OsStr;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::Arguments;
+
+/// The duration that a downloaded source file remains valid for.
+pub const CACHE_LIFETIME: Duration = Duration::from_hours(24);
+
+/// Replaces the given arguments' IP source paths with locally cached copies fetched from their source URL, if present.
+///
+/// # Errors
+///
+/// This function will return an error if the source data could not be downloaded.
+pub fn fetch_sources(mut arguments: Arguments) -> Result<Arguments> {
+    if let Some(url) = arguments.source_url.as_deref() {
+        arguments.ipv4_source = self::fetch_cached(url, &arguments.ipv4_source)?;
+        arguments.ipv6_source = self::fetch_cached(url, &arguments.ipv6_source)?;
+    }
+
+    Ok(arguments)
+}
+
+/// Downloads the file with the given path's file name relative to the base URL, returning the path of its cached copy.
+///
+/// If a cached copy exists and is younger than [`CACHE_LIFETIME`], it is returned without downloading the file.
+///
+/// # Errors
+///
+/// This function will return an error if the file could not be downloaded or cached.
+pub fn fetch_cached(base_url: &str, path: &Path) -> Result<Box<Path>> {
+    let Some(file_name) = path.file_name().and_then(OsStr::to_str) else {
+        bail!("unable to determine the file name of '{}'", path.to_string_lossy());
+    };
+
+    let url = format!("{}/{file_name}", base_url.trim_end_matches('/'));
+    let cache_name: String = url.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    let cache_dir = std::env::temp_dir().join(env!("CARGO_PKG_NAME"));
+    let cache = cache_dir.join(cache_name);
+
+    if let Ok(modified) = std::fs::metadata(&cache).and_then(|m| m.modified()) {
+        if modified.elapsed().is_ok_and(|age| age < CACHE_LIFETIME) {
+            return Ok(cache.into_boxed_path());
+        }
+    }
+
+    std::fs::create_dir_all(&cache_dir)?;
+
+    // Download into a separate file first so that an interrupted download is never mistaken for a valid cache.
+    let partial = cache.with_extension("part");
+    let mut response = reqwest::blocking::get(&url)?.error_for_status()?;
+    let mut file = std::fs::File::create(&partial)?;
+
+    response.copy_to(&mut file)?;
+    std::fs::rename(&partial, &cache)?;
+
+    Ok(cache.into_boxed_path())
+}
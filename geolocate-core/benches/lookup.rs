@@ -0,0 +1,80 @@
+//! Compares address lookups within maps with and without overlapping blocks.
+//!
+//! The benchmark loads a Tor geoip file from the path within `GEOLOCATE_BENCH_GEOIP`, defaulting to
+//! `/usr/share/tor/geoip`. If the file does not exist, a synthetic partition of the address space is used instead.
+
+use std::hint::black_box;
+use std::net::Ipv4Addr;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use geolocate_core::prelude::*;
+
+/// The default location of the Tor geoip file.
+const DEFAULT_GEOIP: &str = "/usr/share/tor/geoip";
+/// The number of addresses looked up per iteration.
+const LOOKUPS: usize = 4096;
+
+/// Loads the geoip map, falling back to a synthetic map if the file does not exist.
+fn load_map() -> Ipv4AddrBlockMap<Box<str>> {
+    let path = std::env::var("GEOLOCATE_BENCH_GEOIP").unwrap_or_else(|_| DEFAULT_GEOIP.to_string());
+
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        eprintln!("unable to read '{path}', using a synthetic map instead");
+
+        return (0 .. 1 << 16)
+            .map(|i: u32| {
+                (Ipv4AddrBlock::new(Ipv4Addr::from_bits(i << 16), Ipv4Addr::from_bits(i << 16 | 0xFFFF)), "??".into())
+            })
+            .collect();
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            let start = Ipv4Addr::from_bits(fields.next()?.parse().ok()?);
+            let end = Ipv4Addr::from_bits(fields.next()?.parse().ok()?);
+
+            Some((Ipv4AddrBlock::new(start, end), fields.next()?.into()))
+        })
+        .collect()
+}
+
+/// Returns a deterministic list of pseudo-random addresses.
+fn addresses() -> Box<[Ipv4Addr]> {
+    let mut state = 0x2545_F491_u32;
+
+    (0 .. LOOKUPS)
+        .map(|_| {
+            // A xorshift generator is plenty for spreading lookups across the address space.
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+
+            Ipv4Addr::from_bits(state)
+        })
+        .collect()
+}
+
+/// Benchmarks lookups within the same map, both as-is and with an overlapping block that disables the fast path.
+fn lookup(c: &mut Criterion) {
+    let disjoint = load_map();
+    let mut overlapping = disjoint.clone();
+    let addresses = self::addresses();
+
+    overlapping.insert(Ipv4AddrBlock::new(Ipv4Addr::from_bits(0), Ipv4Addr::from_bits(u32::MAX)), "??".into());
+
+    let mut group = c.benchmark_group("get_from_address");
+
+    group.bench_function("disjoint", |b| {
+        b.iter(|| addresses.iter().filter(|&&a| disjoint.get_from_address(black_box(a)).is_some()).count());
+    });
+    group.bench_function("overlapping", |b| {
+        b.iter(|| addresses.iter().filter(|&&a| overlapping.get_from_address(black_box(a)).is_some()).count());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookup);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use core::num::ParseIntError;
+
+use crate::ip::{IpAddrBlock, IpAddrBlockMap};
+
+/// An autonomous system number.
+pub type Asn = u32;
+/// A block of autonomous system numbers.
+pub type AsnBlock = IpAddrBlock<Asn>;
+/// An autonomous system number block map.
+pub type AsnBlockMap<T> = IpAddrBlockMap<Asn, T>;
+
+/// Parses an autonomous system number, which may be prefixed with `AS` as in `AS13335`.
+///
+/// # Errors
+///
+/// This function will return an error if the value is not a valid 32-bit autonomous system number.
+pub fn parse_asn(value: &str) -> Result<Asn, ParseIntError> {
+    let value = value.trim();
+    let number = value.strip_prefix("AS").or_else(|| value.strip_prefix("as")).unwrap_or(value);
+
+    number.parse()
+}
@@ -1,64 +1,348 @@
-use std::fmt::{Display, Write};
-use std::str::{Chars, FromStr};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::ToString;
+use core::fmt::{Display, Write};
+use core::str::FromStr;
 
+#[cfg(feature = "serde")]
 use serde::de::{Unexpected, Visitor};
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
 /// An ISO-3166 country.
-#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// When deserialized, each field also accepts the names commonly used by other country datasets:
+///
+/// - `name` may be given as `country_name`.
+/// - `code` may be given as `alpha2`, `alpha_2`, `alpha-2`, `iso2`, or `country_code`.
+/// - `alpha3` may be given as `alpha_3`, `alpha-3`, or `iso3`.
+/// - `numeric` may be given as `iso_numeric`, `numeric_code`, or `country_code_numeric`.
+///
+/// Each field may only be given once, under any one of its names.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Country {
     /// The country's name.
+    #[cfg_attr(feature = "serde", serde(alias = "country_name"))]
     pub name: Box<str>,
     /// The country's code.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "alpha2", alias = "alpha_2", alias = "alpha-2", alias = "iso2", alias = "country_code")
+    )]
     pub code: CountryCode,
-    /// The country's numeric code.
-    pub numeric: u16,
+    /// The country's alpha-3 code, or [`None`] if it is unknown.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none", alias = "alpha_3", alias = "alpha-3", alias = "iso3")
+    )]
+    pub alpha3: Option<CountryCode>,
+    /// The country's numeric code, or [`None`] if it is unknown.
+    #[cfg_attr(
+        feature = "serde",
+        serde(alias = "iso_numeric", alias = "numeric_code", alias = "country_code_numeric")
+    )]
+    pub numeric: Option<NumericCode>,
+    /// The country's name in other languages, keyed by their language codes.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "BTreeMap::is_empty"))]
+    pub names: BTreeMap<Box<str>, Box<str>>,
+    /// The IANA identifiers of the country's timezones, such as `Europe/Berlin`, or an empty list if they are unknown.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "<[_]>::is_empty"))]
+    pub timezones: Box<[Box<str>]>,
 }
 
 impl Country {
     /// Creates a new [`Country`].
+    ///
+    /// This does not validate its arguments, and should only be used with trusted inputs; prefer [`Country::try_new`]
+    /// otherwise.
+    #[inline]
+    pub fn new(name: impl AsRef<str>, code: CountryCode, numeric: Option<NumericCode>) -> Self {
+        Self {
+            name: Box::from(name.as_ref()),
+            code,
+            alpha3: None,
+            numeric,
+            names: BTreeMap::new(),
+            timezones: Box::default(),
+        }
+    }
+
+    /// Creates a new [`Country`], validating each of its arguments.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the name is empty, the code is [`CountryCode::Unassigned`], or the
+    /// numeric code is out of range.
+    pub fn try_new(name: impl AsRef<str>, code: CountryCode, numeric: Option<u16>) -> Result<Self, CountryError> {
+        let name = name.as_ref().trim();
+
+        if name.is_empty() {
+            return Err(CountryError::EmptyName);
+        }
+        if code == CountryCode::Unassigned {
+            return Err(CountryError::UnassignedCode);
+        }
+
+        let numeric = numeric.map(NumericCode::try_from).transpose()?;
+
+        Ok(Self::new(name, code, numeric))
+    }
+
+    /// Returns the country's name in the given language, falling back to its default name if it is not known.
+    #[must_use]
+    pub fn localized_name(&self, language: &str) -> &str {
+        self.names.get(language).unwrap_or(&self.name)
+    }
+
+    /// Compares two countries by their names, falling back to their codes if their names are equal.
+    ///
+    /// Names are compared by their characters, so this is not locale-aware.
+    #[must_use]
+    pub fn cmp_by_name(&self, other: &Self) -> core::cmp::Ordering {
+        self.name.cmp(&other.name).then_with(|| self.cmp_by_code(other))
+    }
+
+    /// Compares two countries by their codes.
     #[inline]
-    pub fn new(name: impl AsRef<str>, code: CountryCode, numeric: u16) -> Self {
-        Self { name: Box::from(name.as_ref()), code, numeric }
+    #[must_use]
+    pub fn cmp_by_code(&self, other: &Self) -> core::cmp::Ordering {
+        self.code.cmp(&other.code)
+    }
+
+    /// Returns a display implementation that summarizes the country's name, code, and numeric code.
+    ///
+    /// This is formatted as `Germany (DE, 276)`.
+    #[inline]
+    #[must_use]
+    pub const fn summary(&self) -> CountrySummary<'_> {
+        CountrySummary(self)
     }
 }
 
 impl PartialOrd for Country {
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
+/// Countries are ordered by their numeric codes, with unknown codes ordered first.
+///
+/// See [`Country::cmp_by_name`] and [`Country::cmp_by_code`] for other orderings.
 impl Ord for Country {
     #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.numeric.cmp(&other.numeric)
     }
 }
 
+/// A display implementation that summarizes a [`Country`].
+///
+/// This is created using [`Country::summary`].
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct CountrySummary<'c>(&'c Country);
+
+impl Display for CountrySummary<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0.numeric {
+            Some(numeric) => write!(f, "{} ({}, {numeric})", self.0.name, self.0.code),
+            None => write!(f, "{} ({}, N/A)", self.0.name, self.0.code),
+        }
+    }
+}
+
 /// An error that is returned when trying to parse an invalid country code.
 #[repr(transparent)]
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct InvalidCodeError(Box<str>);
 
-impl std::error::Error for InvalidCodeError {}
+impl core::error::Error for InvalidCodeError {}
 
 impl Display for InvalidCodeError {
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "invalid country code: {}", self.0)
     }
 }
 
+/// An error that is returned when trying to create an invalid [`Country`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum CountryError {
+    /// The country's name is empty.
+    EmptyName,
+    /// The country's code is unassigned.
+    UnassignedCode,
+    /// The country's numeric code is out of range.
+    InvalidNumeric(InvalidNumericError),
+}
+
+impl core::error::Error for CountryError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidNumeric(error) => Some(error),
+            Self::EmptyName | Self::UnassignedCode => None,
+        }
+    }
+}
+
+impl Display for CountryError {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::EmptyName => f.write_str("invalid country: the name is empty"),
+            Self::UnassignedCode => f.write_str("invalid country: the code is unassigned"),
+            Self::InvalidNumeric(error) => write!(f, "invalid country: {error}"),
+        }
+    }
+}
+
+impl From<InvalidNumericError> for CountryError {
+    #[inline]
+    fn from(value: InvalidNumericError) -> Self {
+        Self::InvalidNumeric(value)
+    }
+}
+
+/// An error that is returned when trying to parse an invalid numeric country code.
+#[repr(transparent)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct InvalidNumericError(Box<str>);
+
+impl core::error::Error for InvalidNumericError {}
+
+impl Display for InvalidNumericError {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid numeric country code: {}", self.0)
+    }
+}
+
+/// A country's ISO-3166 numeric code, which is always within the range `0..=999`.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NumericCode(u16);
+
+impl NumericCode {
+    /// The largest valid numeric code.
+    pub const MAX: u16 = 999;
+
+    /// Creates a new [`NumericCode`], returning [`None`] if the given value is out of range.
+    #[inline]
+    #[must_use]
+    pub const fn new(value: u16) -> Option<Self> {
+        if value <= Self::MAX { Some(Self(value)) } else { None }
+    }
+
+    /// Returns the value of this [`NumericCode`].
+    #[inline]
+    #[must_use]
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for NumericCode {
+    type Error = InvalidNumericError;
+
+    #[inline]
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        Self::new(value).ok_or_else(|| InvalidNumericError(value.to_string().into_boxed_str()))
+    }
+}
+
+impl From<NumericCode> for u16 {
+    #[inline]
+    fn from(value: NumericCode) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for NumericCode {
+    type Err = InvalidNumericError;
+
+    #[inline]
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value.parse().ok().and_then(Self::new).ok_or_else(|| InvalidNumericError(value.into()))
+    }
+}
+
+impl Display for NumericCode {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for NumericCode {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u16(self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for NumericCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct NumericVisitor;
+
+        impl Visitor<'_> for NumericVisitor {
+            type Value = NumericCode;
+
+            #[inline]
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "a numeric country code between 0 and {}", NumericCode::MAX)
+            }
+
+            #[inline]
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                u16::try_from(v)
+                    .ok()
+                    .and_then(NumericCode::new)
+                    .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(v), &self))
+            }
+
+            #[inline]
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                u16::try_from(v)
+                    .ok()
+                    .and_then(NumericCode::new)
+                    .ok_or_else(|| E::invalid_value(Unexpected::Signed(v), &self))
+            }
+        }
+
+        deserializer.deserialize_u16(NumericVisitor)
+    }
+}
+
 /// A country's code.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CountryCode {
-    /// An Alpha-2 code.
+    /// An Alpha-2 code, as defined by ISO 3166-1.
     Alpha2([char; 2]),
-    /// An Alpha-3 code.
+    /// An Alpha-3 code, as defined by ISO 3166-1.
     Alpha3([char; 3]),
-    /// An Alpha-4 code.
+    /// An Alpha-4 code, as defined by ISO 3166-3 for countries whose names are no longer in use.
+    ///
+    /// No current country has an Alpha-4 code. The first two letters are the country's former Alpha-2 code, and the
+    /// last two are the Alpha-2 code of its successor, or `HH` if it has no single successor. For example, `BUMM` is
+    /// Burma, which became Myanmar (`MM`), and `CSHH` is Czechoslovakia.
     Alpha4([char; 4]),
     /// An unassigned code.
     Unassigned,
@@ -85,30 +369,73 @@ impl From<[char; 4]> for CountryCode {
     }
 }
 
+/// Codes must be two to four ASCII characters long, and any code that is not entirely uppercase letters, such as `??`,
+/// is parsed as [`CountryCode::Unassigned`]. Any other string, including one containing non-ASCII characters, is
+/// invalid.
 impl FromStr for CountryCode {
     type Err = InvalidCodeError;
 
     fn from_str(value: &str) -> Result<Self, Self::Err> {
         #[inline]
-        fn array<const N: usize>(iter: &mut Chars<'_>) -> Option<[char; N]> {
-            let array = std::array::from_fn(|_| iter.next().unwrap_or_else(|| unreachable!("a character is missing")));
+        fn array<const N: usize>(bytes: &[u8]) -> Option<[char; N]> {
+            let array = <[u8; N]>::try_from(bytes).ok()?;
 
-            array.iter().all(char::is_ascii_uppercase).then_some(array)
+            array.iter().all(u8::is_ascii_uppercase).then(|| array.map(char::from))
         }
 
-        let mut chars = value.chars();
+        if !value.is_ascii() {
+            return Err(InvalidCodeError(value.into()));
+        }
 
-        Ok(match value.chars().count() {
-            2 => array(&mut chars).map_or(Self::Unassigned, Self::Alpha2),
-            3 => array(&mut chars).map_or(Self::Unassigned, Self::Alpha3),
-            4 => array(&mut chars).map_or(Self::Unassigned, Self::Alpha4),
+        let bytes = value.as_bytes();
+
+        Ok(match bytes.len() {
+            2 => array(bytes).map_or(Self::Unassigned, Self::Alpha2),
+            3 => array(bytes).map_or(Self::Unassigned, Self::Alpha3),
+            4 => array(bytes).map_or(Self::Unassigned, Self::Alpha4),
             _ => return Err(InvalidCodeError(value.into())),
         })
     }
 }
 
+impl CountryCode {
+    /// The final letters of an Alpha-4 code whose country has no single successor.
+    const NO_SUCCESSOR: [char; 2] = ['H', 'H'];
+
+    /// Returns the former Alpha-2 code of an Alpha-4 code, or [`None`] if this is not an Alpha-4 code.
+    #[inline]
+    #[must_use]
+    pub const fn former_alpha2(self) -> Option<Self> {
+        match self {
+            Self::Alpha4([a, b, _, _]) => Some(Self::Alpha2([a, b])),
+            _ => None,
+        }
+    }
+
+    /// Returns the Alpha-2 code of the successor of an Alpha-4 code's country.
+    ///
+    /// This returns [`None`] if this is not an Alpha-4 code, or if its country has no single successor.
+    #[inline]
+    #[must_use]
+    pub const fn successor_alpha2(self) -> Option<Self> {
+        match self {
+            Self::Alpha4([_, _, c, d]) if c != Self::NO_SUCCESSOR[0] || d != Self::NO_SUCCESSOR[1] => {
+                Some(Self::Alpha2([c, d]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a display implementation that formats unassigned codes as the given string, rather than `??`.
+    #[inline]
+    #[must_use]
+    pub const fn display_with(self, unassigned: &str) -> CodeDisplay<'_> {
+        CodeDisplay { code: self, unassigned }
+    }
+}
+
 impl Display for CountryCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let slice: &[char] = match self {
             Self::Alpha2(array) => array,
             Self::Alpha3(array) => array,
@@ -120,6 +447,23 @@ impl Display for CountryCode {
     }
 }
 
+/// A display implementation for a [`CountryCode`] that formats unassigned codes as a custom string.
+///
+/// This is created using [`CountryCode::display_with`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct CodeDisplay<'s> {
+    code: CountryCode,
+    unassigned: &'s str,
+}
+
+impl Display for CodeDisplay<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.code == CountryCode::Unassigned { f.write_str(self.unassigned) } else { self.code.fmt(f) }
+    }
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for CountryCode {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -130,6 +474,7 @@ impl Serialize for CountryCode {
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for CountryCode {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -141,7 +486,7 @@ impl<'de> Deserialize<'de> for CountryCode {
             type Value = CountryCode;
 
             #[inline]
-            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
                 write!(f, "a valid country code")
             }
 
@@ -157,3 +502,137 @@ impl<'de> Deserialize<'de> for CountryCode {
         deserializer.deserialize_str(CodeVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+    use core::cmp::Ordering;
+    use core::str::FromStr;
+
+    use super::{Country, CountryCode, NumericCode};
+
+    /// Returns a country with the given name, alpha-2 code, and numeric code.
+    fn country(name: &str, code: [char; 2], numeric: Option<u16>) -> Country {
+        Country::new(name, CountryCode::Alpha2(code), numeric.and_then(NumericCode::new))
+    }
+
+    /// Returns the codes of the given countries after sorting them using the given comparator.
+    fn sorted_codes(mut countries: Vec<Country>, compare: fn(&Country, &Country) -> Ordering) -> Vec<CountryCode> {
+        countries.sort_by(compare);
+        countries.into_iter().map(|country| country.code).collect()
+    }
+
+    /// Returns a set of countries whose name, code, and numeric orderings all differ.
+    fn countries() -> Vec<Country> {
+        alloc::vec![
+            self::country("Germany", ['D', 'E'], Some(276)),
+            self::country("Australia", ['A', 'U'], Some(36)),
+            self::country("Kosovo", ['X', 'K'], None),
+            self::country("Switzerland", ['C', 'H'], Some(756)),
+        ]
+    }
+
+    #[test]
+    fn country_orders_by_numeric_code() {
+        let codes = self::sorted_codes(self::countries(), Country::cmp);
+
+        assert_eq!(codes, [
+            CountryCode::Alpha2(['X', 'K']),
+            CountryCode::Alpha2(['A', 'U']),
+            CountryCode::Alpha2(['D', 'E']),
+            CountryCode::Alpha2(['C', 'H']),
+        ]);
+    }
+
+    #[test]
+    fn country_orders_by_name() {
+        let codes = self::sorted_codes(self::countries(), Country::cmp_by_name);
+
+        assert_eq!(codes, [
+            CountryCode::Alpha2(['A', 'U']),
+            CountryCode::Alpha2(['D', 'E']),
+            CountryCode::Alpha2(['X', 'K']),
+            CountryCode::Alpha2(['C', 'H']),
+        ]);
+    }
+
+    #[test]
+    fn country_orders_by_code() {
+        let codes = self::sorted_codes(self::countries(), Country::cmp_by_code);
+
+        assert_eq!(codes, [
+            CountryCode::Alpha2(['A', 'U']),
+            CountryCode::Alpha2(['C', 'H']),
+            CountryCode::Alpha2(['D', 'E']),
+            CountryCode::Alpha2(['X', 'K']),
+        ]);
+    }
+
+    #[test]
+    fn country_name_ties_order_by_code() {
+        let first = self::country("Atlantis", ['X', 'A'], Some(901));
+        let second = self::country("Atlantis", ['X', 'B'], Some(900));
+
+        assert_eq!(first.cmp_by_name(&second), Ordering::Less);
+        assert_eq!(second.cmp_by_name(&first), Ordering::Greater);
+        assert_eq!(first.cmp_by_name(&first), Ordering::Equal);
+        assert_eq!(first.cmp(&second), Ordering::Greater);
+    }
+
+    /// Returns the next value of the given xorshift generator, which is enough to produce varied inputs without
+    /// depending on a property testing crate.
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Returns a random string of up to five characters drawn from uppercase letters, other ASCII characters, and any
+    /// Unicode scalar value.
+    fn random_string(state: &mut u64) -> String {
+        let length = self::next_random(state) % 6;
+
+        (0 .. length)
+            .map(|_| {
+                let value = self::next_random(state);
+                let bits = |modulus: u64| u32::try_from((value >> 8) % modulus).unwrap();
+
+                match value % 3 {
+                    0 => char::from_u32(u32::from(b'A') + bits(26)).unwrap(),
+                    1 => char::from_u32(bits(0x80)).unwrap(),
+                    _ => char::from_u32(bits(0x11_0000)).unwrap_or(char::REPLACEMENT_CHARACTER),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn code_parses_random_strings() {
+        let mut state = 0x9E37_79B9_7F4A_7C15;
+
+        for _ in 0 .. 100_000 {
+            let value = self::random_string(&mut state);
+            let result = CountryCode::from_str(&value);
+
+            if !value.is_ascii() || !(2 ..= 4).contains(&value.len()) {
+                assert!(result.is_err(), "{value:?} parsed as {result:?}");
+            } else if value.bytes().all(|b| b.is_ascii_uppercase()) {
+                let code = result.unwrap();
+
+                assert_ne!(code, CountryCode::Unassigned, "{value:?}");
+                assert_eq!(code.to_string(), value);
+            } else {
+                assert_eq!(result, Ok(CountryCode::Unassigned), "{value:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn code_rejects_multibyte_letters() {
+        for value in ["DÉ", "ÄÖ", "ＤＥ", "Dé", "ΑΒ", "ǅE", "DE\u{301}", "\u{1F1E9}\u{1F1EA}"] {
+            assert!(CountryCode::from_str(value).is_err(), "{value:?}");
+        }
+    }
+}
@@ -1,25 +1,103 @@
-use std::cmp::Ordering;
-use std::fmt::Display;
-use std::ops::RangeInclusive;
-
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::ops::RangeInclusive;
+#[cfg(feature = "serde")]
+use core::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::de::{MapAccess, Unexpected, Visitor};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+
+/// The CIDR prefix API.
+pub mod prefix;
 /// The IPv4-specific API.
 pub mod v4;
 /// The IPv6-specific API.
 pub mod v6;
 
-#[doc(hidden)]
-pub(crate) mod private {
-    pub trait Sealed {}
+/// A trait that allows a type of be used within an [`IpAddrBlock<A>`].
+///
+/// While primarily intended for IP addresses, any totally ordered key that can be represented as a fixed-width integer
+/// may implement this trait, allowing the block and map machinery to be reused for things like autonomous system number
+/// ranges.
+///
+/// Implementors must ensure that their [`Ord`] implementation is a total order that agrees with their [`PartialEq`]
+/// implementation, as maps are searched using binary searches, and that it agrees with the order of their bits.
+pub trait Address: Copy + Ord {
+    /// The number of bits within the address.
+    const BITS: u32;
+
+    /// Returns the address as an integer, widened to a [`u128`].
+    fn to_bits(self) -> u128;
+
+    /// Creates an address from the given integer.
+    ///
+    /// Bits above [`BITS`](<Address::BITS>) are discarded.
+    fn from_bits(bits: u128) -> Self;
+
+    /// Returns the number of leading zeros within the address' bits.
+    #[inline]
+    fn leading_zeros(self) -> u32 {
+        self.to_bits().leading_zeros() - (u128::BITS - Self::BITS)
+    }
 }
 
-/// A trait that allows a type of be used within an [`IpAddrBlock<A>`].
-pub trait Address: Copy + Ord + private::Sealed {}
+/// Implements [`Address`] for the given integer types.
+macro_rules! impl_address {
+    ($($type:ty),* $(,)?) => {$(
+        impl Address for $type {
+            const BITS: u32 = <$type>::BITS;
+
+            #[inline]
+            fn to_bits(self) -> u128 {
+                u128::from(self)
+            }
+
+            #[inline]
+            #[allow(clippy::cast_possible_truncation)]
+            fn from_bits(bits: u128) -> Self {
+                bits as $type
+            }
+        }
+    )*};
+}
+
+impl_address!(u8, u16, u32, u64, u128);
+
+/// A report of the changes made and issues found while normalizing an [`IpAddrBlockMap<A, T>`].
+///
+/// This is returned by [`normalize_reporting`](<IpAddrBlockMap::normalize_reporting>).
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct NormalizeReport {
+    /// The number of entries that were removed for sharing a block with an earlier entry.
+    pub duplicates: usize,
+    /// The number of adjacent entries whose blocks overlap after normalizing.
+    pub overlaps: usize,
+}
+
+impl NormalizeReport {
+    /// Returns whether normalizing neither removed any duplicate entries nor found any overlapping blocks.
+    #[inline]
+    #[must_use]
+    pub const fn is_clean(&self) -> bool {
+        self.duplicates == 0 && self.overlaps == 0
+    }
+}
 
 /// A type that allows values to be mapped to IP address blocks.
-#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+///
+/// Blocks may overlap, in which case an address is resolved using the most specific block that contains it: the block
+/// that starts last, and of those, the block that ends first. This is the same block regardless of the order in which
+/// entries were inserted, other than for exact duplicates, of which only the first inserted entry is kept.
+#[derive(Clone, Debug)]
 pub struct IpAddrBlockMap<A: Address, T> {
     inner: Vec<(IpAddrBlock<A>, T)>,
     dirty: bool,
+    /// Whether no two blocks overlap, which allows addresses to be searched for by start address alone.
+    disjoint: bool,
 }
 
 impl<A: Address, T> IpAddrBlockMap<A, T> {
@@ -27,85 +105,323 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
     #[inline]
     #[must_use]
     pub const fn new() -> Self {
-        Self { inner: Vec::new(), dirty: false }
+        Self { inner: Vec::new(), dirty: false, disjoint: true }
     }
 
     /// Creates a new [`IpAddrBlockMap<A, T>`] with the given capacity.
     #[inline]
     #[must_use]
     pub fn with_capacity(capacity: usize) -> Self {
-        Self { inner: Vec::with_capacity(capacity), dirty: false }
+        Self { inner: Vec::with_capacity(capacity), dirty: false, disjoint: true }
     }
 
     /// Returns whether this block map contains the given IP address.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the map is unable to properly search through its inner IP blocks.
     pub fn contains_address(&self, address: A) -> bool {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        self.inner.binary_search_by(|(b, _)| b.partial_cmp(&address).expect("unable to search")).is_ok()
+        self.search_address(address).is_ok()
+    }
+
+    /// Returns whether this block map contains the given IP address, without assuming that the map is normalized.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the map has been modified since it was last normalized.
+    pub fn try_contains_address(&self, address: A) -> Result<bool, DirtyMapError> {
+        self.ensure_normalized()?;
+
+        Ok(self.search_address(address).is_ok())
     }
 
     /// Returns whether this block map contains the given IP address block.
     pub fn contains_block(&self, block: IpAddrBlock<A>) -> bool {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        self.inner.binary_search_by_key(&block, |(b, _)| *b).is_ok()
+        self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block)).is_ok()
     }
 
     /// Returns the number of entries within the map.
     #[inline]
     #[must_use]
-    pub fn len(&self) -> usize {
+    pub const fn len(&self) -> usize {
         self.inner.len()
     }
 
     /// Returns whether the map is empty.
     #[inline]
     #[must_use]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.inner.is_empty()
     }
 
-    /// Returns a value associated with the given IP address.
+    /// Returns whether the map has been modified since it was last normalized.
     ///
-    /// # Panics
+    /// A map in this state must be normalized before it is read from, or lookups may return incorrect results.
+    #[inline]
+    #[must_use]
+    pub const fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Returns whether no two blocks within the map overlap.
+    ///
+    /// This is only known to be accurate once the map has been normalized, and may be `false` for a map whose
+    /// overlapping blocks have since been removed.
+    #[inline]
+    #[must_use]
+    pub const fn is_disjoint(&self) -> bool {
+        self.disjoint
+    }
+
+    /// Returns an error if the map has been modified since it was last normalized.
+    #[inline]
+    const fn ensure_normalized(&self) -> Result<(), DirtyMapError> {
+        if self.dirty { Err(DirtyMapError) } else { Ok(()) }
+    }
+
+    /// Returns the total number of addresses covered by the map's blocks, saturating at [`u128::MAX`].
+    ///
+    /// Addresses covered by several overlapping blocks are only counted once, so this never exceeds the size of the
+    /// address space. If any blocks overlap, they are merged into a temporary list before being counted.
+    #[must_use]
+    pub fn total_covered(&self) -> u128 {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        let sum = |total: u128, block: &IpAddrBlock<A>| total.saturating_add(block.address_count());
+
+        if self.disjoint {
+            return self.inner.iter().map(|(b, _)| b).fold(0, sum);
+        }
+
+        let mut blocks: Vec<_> = self.inner.iter().map(|(b, _)| *b).collect();
+
+        self::merge_blocks(&mut blocks);
+
+        blocks.iter().fold(0, sum)
+    }
+
+    /// Returns the number of entries that the map can hold without reallocating.
+    #[inline]
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    /// Returns the approximate number of bytes used by the map, including any unused capacity.
+    ///
+    /// This does not include memory allocated by the values themselves, such as the contents of a [`Box<str>`].
+    #[inline]
+    #[must_use]
+    pub const fn memory_footprint(&self) -> usize {
+        core::mem::size_of::<Self>() + self.inner.capacity() * core::mem::size_of::<(IpAddrBlock<A>, T)>()
+    }
+
+    /// Shrinks the capacity of the map as much as possible, for example after removing entries.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.inner.shrink_to_fit();
+    }
+
+    /// Returns the index of the entry containing the given IP address.
+    ///
+    /// If no entry contains the address, this returns the index at which a block containing it would be inserted, which
+    /// is also the index of the first entry that starts after it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing the insertion index if no entry contains the address.
+    pub fn index_of_address(&self, address: A) -> Result<usize, usize> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.search_address(address)
+    }
+
+    /// Returns the index of the entry with the given IP address block.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error containing the index at which the block would be inserted if no entry has the
+    /// block.
+    pub fn index_of_block(&self, block: IpAddrBlock<A>) -> Result<usize, usize> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block))
+    }
+
+    /// Returns the value of the most specific block containing the given IP address.
     ///
-    /// Panics if the map is unable to properly search through its inner IP blocks.
+    /// If no blocks overlap, this is found using a single binary search. Otherwise, every block that starts before the
+    /// address may need to be checked. See [`IpAddrBlockMap<A, T>`] for how overlapping blocks are resolved.
     pub fn get_from_address(&self, address: A) -> Option<&T> {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        let index = self.inner.binary_search_by(|(b, _)| {
-            // This should never fail, assuming the PartialOrd impl is correct.
-            b.partial_cmp(&address).expect("unable to search")
-        });
+        let index = self.search_address(address);
 
         self.inner.get(index.ok()?).map(|(_, v)| v)
     }
 
-    /// Returns a value associated with the given IP address.
+    /// Returns a value associated with the given IP address, without assuming that the map is normalized.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if the map is unable to properly search through its inner IP blocks.
+    /// This function will return an error if the map has been modified since it was last normalized.
+    pub fn try_get_from_address(&self, address: A) -> Result<Option<&T>, DirtyMapError> {
+        self.ensure_normalized()?;
+
+        let index = self.search_address(address);
+
+        Ok(index.ok().and_then(|index| self.inner.get(index)).map(|(_, v)| v))
+    }
+
+    /// Returns the value of the most specific block containing the given IP address.
     pub fn get_from_address_mut(&mut self, address: A) -> Option<&mut T> {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        let index = self.inner.binary_search_by(|(b, _)| {
-            // This should never fail, assuming the PartialOrd impl is correct.
-            b.partial_cmp(&address).expect("unable to search")
-        });
+        let index = self.search_address(address);
 
         self.inner.get_mut(index.ok()?).map(|(_, v)| v)
     }
 
+    /// Returns the index, block, and value of the most specific entry containing the given IP address.
+    pub fn get_entry_from_address(&self, address: A) -> Option<(usize, &IpAddrBlock<A>, &T)> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        let index = self.search_address(address).ok()?;
+
+        self.inner.get(index).map(|(b, v)| (index, b, v))
+    }
+
+    /// Returns the index, block, and value of the entry containing the given IP address, without assuming that the
+    /// map is normalized.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the map has been modified since it was last normalized.
+    #[allow(clippy::type_complexity)]
+    pub fn try_get_entry_from_address(
+        &self,
+        address: A,
+    ) -> Result<Option<(usize, &IpAddrBlock<A>, &T)>, DirtyMapError> {
+        self.ensure_normalized()?;
+
+        let Ok(index) = self.search_address(address) else { return Ok(None) };
+
+        Ok(self.inner.get(index).map(|(b, v)| (index, b, v)))
+    }
+
+    /// Returns the values of every entry whose block contains the given IP address, ordered by their blocks.
+    ///
+    /// Unlike [`get_from_address`](<IpAddrBlockMap::get_from_address>), which only returns a single value, this finds
+    /// every conflicting assignment within a map whose blocks overlap. Doing so requires checking each block that
+    /// starts before the address, so this is only as fast as a single search if no blocks overlap.
+    pub fn get_all_from_address(&self, address: A) -> Vec<&T> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        if self.disjoint {
+            return self.get_from_address(address).into_iter().collect();
+        }
+
+        // A block that starts earlier may extend past any number of later blocks, so none can be skipped.
+        let end = self.inner.partition_point(|(b, _)| b.start() <= address);
+
+        self.inner[.. end].iter().filter(|(b, _)| address <= b.end()).map(|(_, v)| v).collect()
+    }
+
+    /// Returns the values associated with each of the given IP addresses, in the same order as the addresses.
+    ///
+    /// Rather than searching the map once per address, this sorts the addresses and resolves them all within a single
+    /// pass over the map, which is considerably faster for large batches. If any blocks overlap, each address is
+    /// instead searched for separately, so that it resolves to the same value as
+    /// [`get_from_address`](<IpAddrBlockMap::get_from_address>).
+    pub fn get_many_from_addresses<'a>(&'a self, addresses: &[A]) -> Vec<Option<&'a T>> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        if !self.disjoint {
+            return addresses.iter().map(|address| self.get_from_address(*address)).collect();
+        }
+
+        let mut order: Vec<usize> = (0 .. addresses.len()).collect();
+        let mut values = alloc::vec![None; addresses.len()];
+        let mut entries = self.inner.iter().peekable();
+
+        order.sort_unstable_by_key(|&index| addresses[index]);
+
+        for index in order {
+            let address = addresses[index];
+
+            // Skip any blocks that end before the address, as later addresses can never be contained by them.
+            while entries.next_if(|(b, _)| b.end() < address).is_some() {}
+
+            if let Some((block, value)) = entries.peek() {
+                if block.start() <= address {
+                    values[index] = Some(value);
+                }
+            }
+        }
+
+        values
+    }
+
+    /// Returns the entries surrounding the given IP address.
+    ///
+    /// The first entry is the closest block that ends before the address, and the second is the closest block that
+    /// starts after it. If the address is contained within a block, that block is skipped.
+    #[allow(clippy::type_complexity)]
+    pub fn surrounding(&self, address: A) -> (Option<(&IpAddrBlock<A>, &T)>, Option<(&IpAddrBlock<A>, &T)>) {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        let index = self.search_address(address);
+        let (below, above) = match index {
+            Ok(index) => (index.checked_sub(1), index + 1),
+            Err(index) => (index.checked_sub(1), index),
+        };
+        let entry = |index: usize| self.inner.get(index).map(|(b, v)| (b, v));
+
+        (below.and_then(entry), entry(above))
+    }
+
+    /// Returns an iterator over every entry whose block shares at least one address with the given block, in sorted
+    /// order.
+    ///
+    /// If no blocks overlap each other, both ends of the matching entries are found using binary searches. Otherwise,
+    /// every block that starts before the given block ends must be checked.
+    pub fn overlapping(&self, block: IpAddrBlock<A>) -> impl Iterator<Item = (&IpAddrBlock<A>, &T)> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        let end = self.inner.partition_point(|(b, _)| b.start() <= block.end());
+        // Since no blocks overlap, their ends are sorted as well as their starts.
+        let start =
+            if self.disjoint { self.inner[.. end].partition_point(|(b, _)| b.end() < block.start()) } else { 0 };
+
+        self.inner[start .. end].iter().filter(move |(b, _)| b.overlaps(&block)).map(|(b, v)| (b, v))
+    }
+
+    /// Searches the map for the most specific block containing the given IP address, returning its index if found, or
+    /// the index at which a block containing it could be inserted otherwise.
+    fn search_address(&self, address: A) -> Result<usize, usize> {
+        let index = self.inner.partition_point(|(b, _)| b.start() <= address);
+
+        if self.disjoint {
+            // Since no blocks overlap, the only block that may contain the address is the last to start before it.
+            return match index.checked_sub(1) {
+                Some(previous) if address <= self.inner[previous].0.end() => Ok(previous),
+                _ => Err(index),
+            };
+        }
+
+        // A block that starts earlier may extend past any number of later blocks, so none can be skipped.
+        let Some(last) = self.inner[.. index].iter().rposition(|(b, _)| address <= b.end()) else { return Err(index) };
+        let start = self.inner[last].0.start();
+
+        // Blocks with the same start are sorted by their ends, so the first of them to contain the address ends first.
+        Ok(self.inner[..= last].partition_point(|(b, _)| (b.start(), b.end()) < (start, address)))
+    }
+
     /// Returns a value associated with the given IP address block.
     pub fn get_from_block(&self, block: IpAddrBlock<A>) -> Option<&T> {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        let index = self.inner.binary_search_by_key(&block, |(b, _)| *b);
+        let index = self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block));
 
         self.inner.get(index.ok()?).map(|(_, v)| v)
     }
@@ -114,18 +430,63 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
     pub fn get_from_block_mut(&mut self, block: IpAddrBlock<A>) -> Option<&mut T> {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        let index = self.inner.binary_search_by_key(&block, |(b, _)| *b);
+        let index = self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block));
 
         self.inner.get_mut(index.ok()?).map(|(_, v)| v)
     }
 
     /// Normalizes the internal map of this [`IpAddrBlockMap<A, T>`].
     pub fn normalize(&mut self) {
+        self.normalize_reporting();
+    }
+
+    /// Normalizes the internal map of this [`IpAddrBlockMap<A, T>`], returning a report of the duplicate blocks that
+    /// were removed and the overlapping blocks that remain.
+    pub fn normalize_reporting(&mut self) -> NormalizeReport {
+        let length = self.inner.len();
+
+        // A stable sort ensures that the first of any duplicate blocks is the one that is retained.
+        self.inner.sort_by(|(a, _), (b, _)| a.cmp_by_start(b));
         self.inner.dedup_by(|(a, _), (b, _)| a == b);
-        self.inner.sort_unstable_by_key(|(b, _)| (b.0, b.1));
         self.inner.shrink_to_fit();
 
+        let overlaps = self.inner.windows(2).filter(|pair| pair[0].0.overlaps(&pair[1].0)).count();
+
         self.dirty = false;
+        self.disjoint = overlaps == 0;
+
+        NormalizeReport { duplicates: length - self.inner.len(), overlaps }
+    }
+
+    /// Merges each run of touching entries with equal values into a single entry, as compared by [`PartialEq`].
+    ///
+    /// See [`coalesce_by`](<IpAddrBlockMap::coalesce_by>) for details.
+    pub fn coalesce(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.coalesce_by(T::eq);
+    }
+
+    /// Merges each run of touching entries with equal values into a single entry, as compared by the given function.
+    ///
+    /// Entries touch if their blocks overlap or are adjacent, where one block's end address is directly followed by the
+    /// next block's start address. The merged entry keeps the value of the first entry within the run.
+    pub fn coalesce_by<F: FnMut(&T, &T) -> bool>(&mut self, mut eq: F) {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        // Each entry is compared against the last retained entry, which absorbs it if they touch and are equal.
+        self.inner.dedup_by(|(block, value), (last, last_value)| {
+            let merges = block.0.to_bits() <= last.1.to_bits().saturating_add(1) && eq(last_value, value);
+
+            if merges {
+                last.1 = last.1.max(block.1);
+            }
+
+            merges
+        });
+
+        self.disjoint = self.inner.windows(2).all(|pair| pair[0].0.is_disjoint(&pair[1].0));
     }
 
     /// Inserts a block-assigned value into the map, without ensuring that it is sorted afterwards.
@@ -136,8 +497,10 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
     ///
     /// You must manually ensure that, before calling any method that attempts to search the map, that the inner map is
     /// sorted. This can be done using [`normalize`](<IpAddrBlockMap::normalize>).
+    ///
+    /// When only inserting a few entries, [`insert`](<IpAddrBlockMap::insert>) avoids the need to normalize.
     pub fn insert_unstable(&mut self, block: IpAddrBlock<A>, value: T) -> Option<T> {
-        let index = if self.dirty { Err(0) } else { self.inner.binary_search_by_key(&block, |(b, _)| *b) };
+        let index = if self.dirty { Err(0) } else { self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block)) };
         let previous = index.ok().map(|i| self.inner.swap_remove(i).1);
 
         self.inner.push((block, value));
@@ -155,21 +518,33 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
     pub fn remove_unstable(&mut self, block: IpAddrBlock<A>) -> Option<T> {
         debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
 
-        let index = self.inner.binary_search_by_key(&block, |(b, _)| *b).ok()?;
+        let index = self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block)).ok()?;
 
         Some(self.inner.swap_remove(index).1)
     }
 
     /// Inserts a block-assigned value into the map, returning the previous value if present.
+    ///
+    /// The entry is placed using a binary search, so that the map remains sorted without being normalized again. A
+    /// previous value is only replaced if its block is exactly equal to the given block. This takes `O(n)` time to
+    /// shift the following entries, which is far cheaper than normalizing when applying a few changes to a large map.
+    ///
+    /// When inserting many entries at once, prefer calling [`insert_unstable`](<IpAddrBlockMap::insert_unstable>) for
+    /// each of them, or [`Extend`], followed by a single call to [`normalize`](<IpAddrBlockMap::normalize>).
     pub fn insert(&mut self, block: IpAddrBlock<A>, value: T) -> Option<T> {
         // Ensure that we normalize so that the return value works properly.
         if self.dirty {
             self.normalize();
         }
 
-        match self.inner.binary_search_by_key(&block, |(b, _)| *b) {
-            Ok(index) => Some(std::mem::replace(&mut self.inner[index], (block, value)).1),
+        match self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block)) {
+            Ok(index) => Some(core::mem::replace(&mut self.inner[index], (block, value)).1),
             Err(index) => {
+                let before = index.checked_sub(1).and_then(|i| self.inner.get(i));
+                let after = self.inner.get(index);
+
+                self.disjoint &= before.is_none_or(|(b, _)| b.is_disjoint(&block));
+                self.disjoint &= after.is_none_or(|(b, _)| b.is_disjoint(&block));
                 self.inner.insert(index, (block, value));
                 None
             }
@@ -183,15 +558,103 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
             self.normalize();
         }
 
-        let index = self.inner.binary_search_by_key(&block, |(b, _)| *b).ok()?;
+        let index = self.inner.binary_search_by(|(b, _)| b.cmp_by_start(&block)).ok()?;
 
         Some(self.inner.remove(index).1)
     }
 
+    /// Retains only the entries for which the given predicate returns `true`.
+    ///
+    /// This preserves the order of the remaining entries, so the map does not need to be normalized afterwards.
+    pub fn retain<F: FnMut(&IpAddrBlock<A>, &mut T) -> bool>(&mut self, mut f: F) {
+        self.inner.retain_mut(|(b, v)| f(b, v));
+    }
+
+    /// Removes and returns every entry for which the given predicate returns `true`, in sorted order.
+    ///
+    /// This preserves the order of both the removed and remaining entries, so neither needs to be normalized
+    /// afterwards. For example, this may be used to split a map into several maps in a single pass.
+    pub fn extract_if<F: FnMut(&IpAddrBlock<A>, &T) -> bool>(&mut self, mut f: F) -> Vec<(IpAddrBlock<A>, T)> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.inner.extract_if(.., |(b, v)| f(b, v)).collect()
+    }
+
+    /// Removes the addresses within the given block from every entry for which the given predicate returns `true`.
+    ///
+    /// Entries that are partially covered by the block are shrunk, or split in two if the block lies strictly within
+    /// them, with each piece keeping a clone of the entry's value. Entries that are covered entirely are removed. This
+    /// preserves the order of the map, so it does not need to be normalized afterwards.
+    pub fn subtract_if<F: FnMut(&IpAddrBlock<A>, &T) -> bool>(&mut self, block: IpAddrBlock<A>, mut f: F)
+    where
+        T: Clone,
+    {
+        if self.dirty {
+            self.normalize();
+        }
+
+        let end = self.inner.partition_point(|(b, _)| b.start() <= block.end());
+        let start =
+            if self.disjoint { self.inner[.. end].partition_point(|(b, _)| b.end() < block.start()) } else { 0 };
+        let mut pieces = Vec::with_capacity(end - start + 1);
+
+        for (b, value) in self.inner.drain(start .. end) {
+            if !b.overlaps(&block) || !f(&b, &value) {
+                pieces.push((b, value));
+
+                continue;
+            }
+
+            match b.difference(&block) {
+                (Some(before), Some(after)) => pieces.extend([(before, value.clone()), (after, value)]),
+                (Some(piece), None) | (None, Some(piece)) => pieces.push((piece, value)),
+                (None, None) => {}
+            }
+        }
+
+        self.inner.splice(start .. start, pieces);
+
+        // If blocks overlap, a piece after the block may start after later entries that overlap its original block.
+        if !self.disjoint {
+            self.inner.sort_by(|(a, _), (b, _)| a.cmp_by_start(b));
+        }
+    }
+
     /// Removes all elements from the map.
     pub fn clear(&mut self) {
         self.inner.clear();
         self.dirty = false;
+        self.disjoint = true;
+    }
+
+    /// Returns a slice of the entries within this map, in sorted order.
+    ///
+    /// This allows for indexed access, for example to display a single page of entries.
+    #[inline]
+    #[must_use]
+    pub fn entries(&self) -> &[(IpAddrBlock<A>, T)] {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        &self.inner
+    }
+
+    /// Returns a slice of the entries within this map, in sorted order, without assuming that the map is normalized.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the map has been modified since it was last normalized.
+    #[inline]
+    pub fn try_entries(&self) -> Result<&[(IpAddrBlock<A>, T)], DirtyMapError> {
+        self.ensure_normalized().map(|()| &*self.inner)
+    }
+
+    /// Returns a reference to the entry at the given index within this map.
+    #[inline]
+    #[must_use]
+    pub fn get_index(&self, index: usize) -> Option<(&IpAddrBlock<A>, &T)> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.inner.get(index).map(|(b, v)| (b, v))
     }
 
     /// Returns an iterator of references to the blocks within this map.
@@ -228,10 +691,174 @@ impl<A: Address, T> IpAddrBlockMap<A, T> {
 
         self.inner.iter_mut().map(|(b, v)| (&*b, v))
     }
+
+    /// Returns the entries of this map as disjoint blocks, sorted by start address.
+    ///
+    /// If any blocks overlap, the address space is split at every block's boundaries, and each piece is assigned the
+    /// value that the map resolves its addresses to. Adjacent pieces resolved using the same entry are merged back
+    /// together.
+    #[must_use]
+    pub fn flatten(&self) -> Vec<(IpAddrBlock<A>, &T)> {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        if self.disjoint {
+            return self.inner.iter().map(|(b, v)| (*b, v)).collect();
+        }
+
+        let max = A::from_bits(u128::MAX).to_bits();
+        let mut boundaries: Vec<u128> = self
+            .inner
+            .iter()
+            .flat_map(|(b, _)| {
+                [Some(b.start().to_bits()), Some(b.end().to_bits()).filter(|end| *end < max).map(|end| end + 1)]
+            })
+            .flatten()
+            .collect();
+
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut records: Vec<(IpAddrBlock<A>, &T, usize)> = Vec::with_capacity(boundaries.len());
+
+        for (index, start) in boundaries.iter().enumerate() {
+            // Every address between two boundaries is contained within the same blocks, so it resolves the same way.
+            let end = boundaries.get(index + 1).map_or(max, |next| next - 1);
+            let Some((entry, _, value)) = self.get_entry_from_address(A::from_bits(*start)) else { continue };
+
+            match records.last_mut() {
+                Some((last, _, last_entry)) if *last_entry == entry && last.end().to_bits() + 1 == *start => {
+                    last.1 = A::from_bits(end);
+                }
+                _ => records.push((IpAddrBlock(A::from_bits(*start), A::from_bits(end)), value, entry)),
+            }
+        }
+
+        records.into_iter().map(|(block, value, _)| (block, value)).collect()
+    }
+
+    /// Returns an iterator over the unassigned address ranges within this map.
+    ///
+    /// This spans the entire address space, including the ranges before the first block and after the last block.
+    pub fn gaps(&self) -> impl Iterator<Item = IpAddrBlock<A>> + '_ {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        // Converting from the maximum integer discards any bits above the address' width.
+        let max = A::from_bits(u128::MAX).to_bits();
+        let mut next = Some(0);
+        let mut blocks = self.inner.iter().map(|(b, _)| (b.start().to_bits(), b.end().to_bits()));
+
+        core::iter::from_fn(move || loop {
+            let start = next?;
+
+            let Some((block_start, block_end)) = blocks.next() else {
+                next = None;
+
+                return Some(IpAddrBlock(A::from_bits(start), A::from_bits(max)));
+            };
+
+            next = (block_end < max).then(|| (block_end + 1).max(start));
+
+            if block_start > start {
+                return Some(IpAddrBlock(A::from_bits(start), A::from_bits(block_start - 1)));
+            }
+        })
+    }
+}
+
+impl<A: Address, T: Clone> IpAddrBlockMap<A, T> {
+    /// Merges the entries of the given map into this map.
+    ///
+    /// Ranges covered by only one map keep their value, while ranges covered by both maps are assigned the value
+    /// returned by `resolve_conflict`, which is given this map's value followed by the other map's value. Blocks that
+    /// partially overlap are split so that each conflicting range is resolved separately.
+    ///
+    /// If either map contains overlapping blocks, it is first [flattened](<IpAddrBlockMap::flatten>) so that each of
+    /// its addresses keeps its most specific value. The result never contains overlapping blocks. For maps without
+    /// overlapping blocks, this runs in `O(n + m)` time, where `n` and `m` are the lengths of each map.
+    pub fn union_with<F: FnMut(&T, &T) -> T>(&mut self, other: &Self, mut resolve_conflict: F) {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+        debug_assert!(!other.dirty, "attempted to read from the map without normalizing");
+
+        let mut inner = Vec::with_capacity(self.inner.len() + other.inner.len());
+        let mut push = |start: u128, end: u128, value: T| {
+            inner.push((IpAddrBlock(A::from_bits(start), A::from_bits(end)), value));
+        };
+
+        let mut left_iter = self.flatten().into_iter().map(|(b, v)| (b.start().to_bits(), b.end().to_bits(), v));
+        let mut right_iter = other.flatten().into_iter().map(|(b, v)| (b.start().to_bits(), b.end().to_bits(), v));
+        let mut left = left_iter.next();
+        let mut right = right_iter.next();
+
+        loop {
+            match (left, right) {
+                (None, None) => break,
+                (Some((start, end, value)), None) => {
+                    push(start, end, value.clone());
+                    left = left_iter.next();
+                }
+                (None, Some((start, end, value))) => {
+                    push(start, end, value.clone());
+                    right = right_iter.next();
+                }
+                (Some((l_start, l_end, l_value)), Some((r_start, r_end, r_value))) => {
+                    if l_end < r_start {
+                        push(l_start, l_end, l_value.clone());
+                        left = left_iter.next();
+                    } else if r_end < l_start {
+                        push(r_start, r_end, r_value.clone());
+                        right = right_iter.next();
+                    } else if l_start < r_start {
+                        // Split off the part of the left block that precedes the overlap.
+                        push(l_start, r_start - 1, l_value.clone());
+                        left = Some((r_start, l_end, l_value));
+                    } else if r_start < l_start {
+                        push(r_start, l_start - 1, r_value.clone());
+                        right = Some((l_start, r_end, r_value));
+                    } else {
+                        let end = l_end.min(r_end);
+
+                        push(l_start, end, resolve_conflict(l_value, r_value));
+
+                        left = if l_end > end { Some((end + 1, l_end, l_value)) } else { left_iter.next() };
+                        right = if r_end > end { Some((end + 1, r_end, r_value)) } else { right_iter.next() };
+                    }
+                }
+            }
+        }
+
+        self.inner = inner;
+        // The union of two flattened maps never contains overlapping blocks itself.
+        self.disjoint = true;
+    }
+}
+
+impl<A: Address, T> Default for IpAddrBlockMap<A, T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// These only compare the entries themselves, since the remaining fields are cached state that two maps with the same
+// entries may disagree on, such as a map whose overlapping blocks have since been removed.
+impl<A: Address, T: PartialEq> PartialEq for IpAddrBlockMap<A, T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<A: Address, T: Eq> Eq for IpAddrBlockMap<A, T> {}
+
+impl<A: Address + Hash, T: Hash> Hash for IpAddrBlockMap<A, T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state);
+    }
 }
 
 impl<A: Address, T> IntoIterator for IpAddrBlockMap<A, T> {
-    type IntoIter = std::vec::IntoIter<Self::Item>;
+    type IntoIter = alloc::vec::IntoIter<Self::Item>;
     type Item = (IpAddrBlock<A>, T);
 
     fn into_iter(self) -> Self::IntoIter {
@@ -241,9 +868,116 @@ impl<A: Address, T> IntoIterator for IpAddrBlockMap<A, T> {
     }
 }
 
+impl<'a, A: Address, T> IntoIterator for &'a IpAddrBlockMap<A, T> {
+    type IntoIter =
+        core::iter::Map<core::slice::Iter<'a, (IpAddrBlock<A>, T)>, fn(&'a (IpAddrBlock<A>, T)) -> Self::Item>;
+    type Item = (&'a IpAddrBlock<A>, &'a T);
+
+    fn into_iter(self) -> Self::IntoIter {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.inner.iter().map(|(b, v)| (b, v))
+    }
+}
+
+impl<'a, A: Address, T> IntoIterator for &'a mut IpAddrBlockMap<A, T> {
+    type IntoIter =
+        core::iter::Map<core::slice::IterMut<'a, (IpAddrBlock<A>, T)>, fn(&'a mut (IpAddrBlock<A>, T)) -> Self::Item>;
+    type Item = (&'a IpAddrBlock<A>, &'a mut T);
+
+    fn into_iter(self) -> Self::IntoIter {
+        debug_assert!(!self.dirty, "attempted to read from the map without normalizing");
+
+        self.inner.iter_mut().map(|(b, v)| (&*b, v))
+    }
+}
+
+impl<A: Address, T> IpAddrBlockMap<A, T> {
+    /// Creates a new [`IpAddrBlockMap<A, T>`] from the given entries, failing if any of their blocks overlap.
+    ///
+    /// Unlike [`FromIterator`], this rejects blocks that partially overlap rather than keeping both. Entries that share
+    /// the same block are still allowed, and only the first of them is kept.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any two of the given blocks overlap without being equal.
+    pub fn try_from_iter<I>(iter: I) -> Result<Self, OverlapError<A>>
+    where
+        I: IntoIterator<Item = (IpAddrBlock<A>, T)>,
+    {
+        let mut map = Self::new();
+
+        map.try_extend(iter)?;
+
+        Ok(map)
+    }
+
+    /// Extends this map with the given entries, failing if any of their blocks overlap each other or an existing block.
+    ///
+    /// Unlike [`Extend`], this rejects blocks that partially overlap rather than keeping both. Entries that share the
+    /// same block are still allowed, and only the first of them is kept. If an overlap is found, the map is left
+    /// unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of the given blocks overlap another block without being equal to it.
+    pub fn try_extend<I>(&mut self, iter: I) -> Result<(), OverlapError<A>>
+    where
+        I: IntoIterator<Item = (IpAddrBlock<A>, T)>,
+    {
+        if self.dirty {
+            self.normalize();
+        }
+
+        let mut entries = Vec::from_iter(iter);
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp_by_start(b));
+        entries.dedup_by(|(a, _), (b, _)| a == b);
+
+        // Since the entries are sorted by start address, any block that overlaps another also overlaps the next.
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0.overlaps(&pair[1].0)) {
+            return Err(OverlapError { first: pair[0].0, second: pair[1].0 });
+        }
+
+        for (block, _) in &entries {
+            if let Some((existing, _)) = self.overlapping(*block).find(|(b, _)| *b != block) {
+                let (first, second) =
+                    if existing.cmp_by_start(block).is_le() { (existing, block) } else { (block, existing) };
+
+                return Err(OverlapError { first: *first, second: *second });
+            }
+        }
+
+        self.inner.extend(entries);
+        self.normalize();
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<A: Address + Send + 'static, T: Send + 'static> IpAddrBlockMap<A, T> {
+    /// Builds a map from the entries returned by the given function, calling it on Tokio's blocking thread pool.
+    ///
+    /// This allows large source files to be read and parsed within an asynchronous runtime without blocking it. The
+    /// entries are collected and normalized on the same thread, so the returned map is immediately ready to be queried.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the given function fails, or if its task panicked or was cancelled.
+    pub async fn load_async<F, I, E>(load: F) -> Result<Self, E>
+    where
+        F: FnOnce() -> Result<I, E> + Send + 'static,
+        I: IntoIterator<Item = (IpAddrBlock<A>, T)>,
+        E: From<tokio::task::JoinError> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(|| load().map(Self::from_iter)).await?
+    }
+}
+
 impl<A: Address, T> FromIterator<(IpAddrBlock<A>, T)> for IpAddrBlockMap<A, T> {
     fn from_iter<I: IntoIterator<Item = (IpAddrBlock<A>, T)>>(iter: I) -> Self {
-        let mut map = Self { inner: Vec::from_iter(iter), dirty: true };
+        let mut map = Self { inner: Vec::from_iter(iter), dirty: true, disjoint: false };
 
         map.normalize();
 
@@ -258,17 +992,65 @@ impl<A: Address, T> Extend<(IpAddrBlock<A>, T)> for IpAddrBlockMap<A, T> {
     }
 }
 
-/// An error that is returned when trying to create an [`IpAddrBlock<A>`] using an empty or overlapping address range.
-#[repr(transparent)]
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub struct EmptyBlockError;
+/// An error that is returned when trying to read from an [`IpAddrBlockMap<A, T>`] that has not been normalized.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct DirtyMapError;
 
-impl std::error::Error for EmptyBlockError {}
+impl core::error::Error for DirtyMapError {}
 
-impl Display for EmptyBlockError {
+impl Display for DirtyMapError {
     #[inline]
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "the given range is empty or overlapping")
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("the map must be normalized before it is read from")
+    }
+}
+
+/// An error that is returned when strictly building an [`IpAddrBlockMap<A, T>`] from blocks that overlap.
+///
+/// This is returned by [`try_from_iter`](<IpAddrBlockMap::try_from_iter>) and
+/// [`try_extend`](<IpAddrBlockMap::try_extend>).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct OverlapError<A: Address> {
+    /// The block that starts first.
+    pub first: IpAddrBlock<A>,
+    /// The block that overlaps it.
+    pub second: IpAddrBlock<A>,
+}
+
+impl<A: Address + Debug + Display> core::error::Error for OverlapError<A> {}
+
+impl<A: Address + Display> Display for OverlapError<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Self { first, second } = self;
+
+        write!(f, "the block {} .. {} overlaps the block {} .. {}", first.0, first.1, second.0, second.1)
+    }
+}
+
+/// An error that is returned when trying to create an [`IpAddrBlock<A>`] using an invalid address range.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum BlockError<A: Address> {
+    /// No addresses were given.
+    Empty,
+    /// The given start address is greater than the given end address.
+    Inverted {
+        /// The given start address.
+        start: A,
+        /// The given end address.
+        end: A,
+    },
+}
+
+impl<A: Address + Debug + Display> core::error::Error for BlockError<A> {}
+
+impl<A: Address + Display> Display for BlockError<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no addresses were given"),
+            Self::Inverted { start, end } => {
+                write!(f, "the start address {start} is greater than the end address {end}")
+            }
+        }
     }
 }
 
@@ -293,8 +1075,8 @@ impl<A: Address> IpAddrBlock<A> {
     /// # Errors
     ///
     /// This function will return an error if the given start address is greater than the given end address.
-    pub fn try_new(start: A, end: A) -> Result<Self, EmptyBlockError> {
-        if start <= end { Ok(Self(start, end)) } else { Err(EmptyBlockError) }
+    pub fn try_new(start: A, end: A) -> Result<Self, BlockError<A>> {
+        if start <= end { Ok(Self(start, end)) } else { Err(BlockError::Inverted { start, end }) }
     }
 
     /// Creates a new [`IpAddrBlock<A>`] from the given slice.
@@ -307,9 +1089,9 @@ impl<A: Address> IpAddrBlock<A> {
     ///
     /// This function assumes that the given slice is sorted, meaning that the structure may be malformed if the slice
     /// is not sorted beforehand.
-    pub const unsafe fn from_slice(slice: &[A]) -> Result<Self, EmptyBlockError> {
-        let Some(start) = slice.first() else { return Err(EmptyBlockError) };
-        let Some(end) = slice.first() else { return Err(EmptyBlockError) };
+    pub const unsafe fn from_slice(slice: &[A]) -> Result<Self, BlockError<A>> {
+        let Some(start) = slice.first() else { return Err(BlockError::Empty) };
+        let Some(end) = slice.last() else { return Err(BlockError::Empty) };
 
         Ok(Self(*start, *end))
     }
@@ -322,7 +1104,7 @@ impl<A: Address> IpAddrBlock<A> {
     /// # Errors
     ///
     /// This function will return an error if the given slice is empty.
-    pub fn from_mut_slice(slice: &mut [A]) -> Result<Self, EmptyBlockError> {
+    pub fn from_mut_slice(slice: &mut [A]) -> Result<Self, BlockError<A>> {
         slice.sort_unstable();
 
         unsafe { Self::from_slice(slice) }
@@ -345,26 +1127,134 @@ impl<A: Address> IpAddrBlock<A> {
     pub const fn range(&self) -> RangeInclusive<A> {
         self.start() ..= self.end()
     }
+
+    /// Compares this [`IpAddrBlock<A>`] to another by their start addresses, breaking ties using their end addresses.
+    ///
+    /// For non-overlapping blocks this is a total order by start address alone, and it is the order that the entries
+    /// of an [`IpAddrBlockMap<A, T>`] are sorted and searched by.
+    #[inline]
+    #[must_use]
+    pub fn cmp_by_start(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0).then_with(|| self.1.cmp(&other.1))
+    }
+
+    /// Compares this [`IpAddrBlock<A>`] to the given address.
+    ///
+    /// The block is [`Equal`](<Ordering::Equal>) to any address that it contains, [`Less`](<Ordering::Less>) than any
+    /// address after its end, and [`Greater`](<Ordering::Greater>) than any address before its start. This is the same
+    /// ordering used when comparing a block and an address with the `<` and `>` operators, in either order.
+    #[inline]
+    #[must_use]
+    pub fn cmp_address(&self, address: A) -> Ordering {
+        if address < self.0 {
+            Ordering::Greater
+        } else if self.1 < address {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }
+    }
+
+    /// Returns whether this [`IpAddrBlock<A>`] shares at least one address with the given block.
+    #[inline]
+    #[must_use]
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.0 <= other.1 && other.0 <= self.1
+    }
+
+    /// Returns the block of addresses shared by this [`IpAddrBlock<A>`] and the given block, if any.
+    #[inline]
+    #[must_use]
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| Self(self.0.max(other.0), self.1.min(other.1)))
+    }
+
+    /// Returns the blocks of addresses within this [`IpAddrBlock<A>`] that are not within the given block.
+    ///
+    /// The first block contains the addresses before the given block, and the second contains those after it. Both are
+    /// [`None`] if the given block covers this block entirely, and the first is this block if they do not overlap.
+    #[must_use]
+    pub fn difference(&self, other: &Self) -> (Option<Self>, Option<Self>) {
+        if self.is_disjoint(other) {
+            return (Some(*self), None);
+        }
+
+        let before = (self.0 < other.0).then(|| Self(self.0, A::from_bits(other.0.to_bits() - 1)));
+        let after = (other.1 < self.1).then(|| Self(A::from_bits(other.1.to_bits() + 1), self.1));
+
+        (before, after)
+    }
+
+    /// Returns whether this [`IpAddrBlock<A>`] shares no addresses with the given block.
+    #[inline]
+    #[must_use]
+    pub fn is_disjoint(&self, other: &Self) -> bool {
+        !self.overlaps(other)
+    }
+
+    /// Returns whether every address within the given block is also within this [`IpAddrBlock<A>`].
+    #[inline]
+    #[must_use]
+    pub fn contains_block(&self, other: &Self) -> bool {
+        self.0 <= other.0 && other.1 <= self.1
+    }
+}
+
+/// Merges the given address blocks into the minimal set of blocks that cover the same addresses.
+///
+/// The blocks are sorted by their start addresses, and any that overlap or are adjacent (where one block's end address
+/// is directly followed by the next block's start address) are combined into a single block.
+pub fn merge_blocks<A: Address>(blocks: &mut Vec<IpAddrBlock<A>>) {
+    blocks.sort_unstable_by(IpAddrBlock::cmp_by_start);
+    // Each block is compared against the last retained block, which absorbs it if they touch.
+    blocks.dedup_by(|block, last| {
+        let touches = block.0.to_bits() <= last.1.to_bits().saturating_add(1);
+
+        if touches {
+            last.1 = last.1.max(block.1);
+        }
+
+        touches
+    });
 }
 
 impl<A: Address> PartialEq<A> for IpAddrBlock<A> {
     #[inline]
     fn eq(&self, other: &A) -> bool {
-        self.range().contains(other)
+        // This shares its boundary checks with `partial_cmp`, so the two can never disagree about an address.
+        self.cmp_address(*other).is_eq()
     }
 }
 
 impl<A: Address> PartialOrd<A> for IpAddrBlock<A> {
+    #[inline]
     fn partial_cmp(&self, other: &A) -> Option<Ordering> {
-        Some(match other {
-            v if self.eq(v) => Ordering::Equal,
-            v if &self.1 < v => Ordering::Less,
-            v if &self.0 > v => Ordering::Greater,
-            _ => unreachable!("value could not be compared"),
-        })
+        Some(self.cmp_address(*other))
     }
 }
 
+/// Implements the reverse of the comparisons between [`IpAddrBlock<A>`] and its addresses for each given address type,
+/// so that an address may be compared to a block as in `address < block`.
+macro_rules! impl_block_comparison {
+    ($($type:ty),*) => {$(
+        impl PartialEq<IpAddrBlock<$type>> for $type {
+            #[inline]
+            fn eq(&self, other: &IpAddrBlock<$type>) -> bool {
+                other.eq(self)
+            }
+        }
+
+        impl PartialOrd<IpAddrBlock<$type>> for $type {
+            #[inline]
+            fn partial_cmp(&self, other: &IpAddrBlock<$type>) -> Option<Ordering> {
+                Some(other.cmp_address(*self).reverse())
+            }
+        }
+    )*};
+}
+
+impl_block_comparison!(u8, u16, u32, u64, u128, core::net::Ipv4Addr, core::net::Ipv6Addr);
+
 impl<A: Address> From<A> for IpAddrBlock<A> {
     #[inline]
     fn from(value: A) -> Self {
@@ -374,7 +1264,7 @@ impl<A: Address> From<A> for IpAddrBlock<A> {
 }
 
 impl<A: Address> TryFrom<(A, A)> for IpAddrBlock<A> {
-    type Error = EmptyBlockError;
+    type Error = BlockError<A>;
 
     #[inline]
     fn try_from((start, end): (A, A)) -> Result<Self, Self::Error> {
@@ -383,7 +1273,7 @@ impl<A: Address> TryFrom<(A, A)> for IpAddrBlock<A> {
 }
 
 impl<A: Address, const N: usize> TryFrom<[A; N]> for IpAddrBlock<A> {
-    type Error = EmptyBlockError;
+    type Error = BlockError<A>;
 
     #[inline]
     fn try_from(mut value: [A; N]) -> Result<Self, Self::Error> {
@@ -392,7 +1282,7 @@ impl<A: Address, const N: usize> TryFrom<[A; N]> for IpAddrBlock<A> {
 }
 
 impl<A: Address> TryFrom<&mut [A]> for IpAddrBlock<A> {
-    type Error = EmptyBlockError;
+    type Error = BlockError<A>;
 
     #[inline]
     fn try_from(value: &mut [A]) -> Result<Self, Self::Error> {
@@ -400,11 +1290,286 @@ impl<A: Address> TryFrom<&mut [A]> for IpAddrBlock<A> {
     }
 }
 
-impl<A: Address> TryFrom<Box<[A]>> for IpAddrBlock<A> {
-    type Error = EmptyBlockError;
+impl<A: Address> TryFrom<alloc::boxed::Box<[A]>> for IpAddrBlock<A> {
+    type Error = BlockError<A>;
 
     #[inline]
-    fn try_from(mut value: Box<[A]>) -> Result<Self, Self::Error> {
+    fn try_from(mut value: alloc::boxed::Box<[A]>) -> Result<Self, Self::Error> {
         Self::from_mut_slice(&mut value)
     }
 }
+
+#[cfg(feature = "serde")]
+impl<'de, A> Deserialize<'de> for IpAddrBlock<A>
+where
+    A: Address + Display + FromStr + Deserialize<'de>,
+{
+    /// Deserializes an address block from either a map containing `start` and `end` addresses, or a string containing
+    /// a `start-end` range, a CIDR prefix, or a single address.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct BlockVisitor<A>(core::marker::PhantomData<A>);
+
+        impl<'de, A> Visitor<'de> for BlockVisitor<A>
+        where
+            A: Address + Display + FromStr + Deserialize<'de>,
+        {
+            type Value = IpAddrBlock<A>;
+
+            #[inline]
+            fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(f, "a map of `start` and `end` addresses, a `start-end` range, or a CIDR prefix")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                let parse = |address: &str| {
+                    address.trim().parse().map_err(|_| E::invalid_value(Unexpected::Str(address), &"an ip address"))
+                };
+
+                if let Some((network, length)) = v.split_once('/') {
+                    let network = parse(network)?;
+                    let Ok(length) = length.trim().parse() else {
+                        return Err(E::invalid_value(Unexpected::Str(length), &"a prefix length"));
+                    };
+
+                    return self::prefix::IpAddrPrefix::new(network, length)
+                        .map(|prefix| prefix.block())
+                        .ok_or_else(|| E::invalid_value(Unexpected::Str(v), &"a valid CIDR prefix"));
+                }
+
+                if let Some((start, end)) = v.split_once('-') {
+                    return IpAddrBlock::try_new(parse(start)?, parse(end)?).map_err(E::custom);
+                }
+
+                parse(v).map(IpAddrBlock::from)
+            }
+
+            fn visit_map<M>(self, mut map: M) -> Result<Self::Value, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                const FIELDS: &[&str] = &["start", "end"];
+
+                let mut start = None;
+                let mut end = None;
+
+                while let Some(key) = map.next_key::<alloc::boxed::Box<str>>()? {
+                    let field = match &*key {
+                        "start" => &mut start,
+                        "end" => &mut end,
+                        key => return Err(serde::de::Error::unknown_field(key, FIELDS)),
+                    };
+
+                    if field.is_some() {
+                        return Err(serde::de::Error::custom(alloc::format!("duplicate field `{key}`")));
+                    }
+
+                    *field = Some(map.next_value()?);
+                }
+
+                let start = start.ok_or_else(|| serde::de::Error::missing_field("start"))?;
+                let end = end.ok_or_else(|| serde::de::Error::missing_field("end"))?;
+
+                IpAddrBlock::try_new(start, end).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(BlockVisitor(core::marker::PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::{IpAddrBlock, IpAddrBlockMap};
+
+    /// Returns the IPv4 block between the given addresses, given as bits.
+    fn block(start: u32, end: u32) -> IpAddrBlock<Ipv4Addr> {
+        IpAddrBlock::new(Ipv4Addr::from_bits(start), Ipv4Addr::from_bits(end))
+    }
+
+    /// Returns a map of the given IPv4 blocks, given as bits, and their values.
+    fn map(entries: &[(u32, u32, char)]) -> IpAddrBlockMap<Ipv4Addr, char> {
+        entries.iter().map(|&(start, end, value)| (self::block(start, end), value)).collect()
+    }
+
+    /// Returns the entries of the given map, with each block given as bits.
+    fn entries(map: &IpAddrBlockMap<Ipv4Addr, char>) -> Vec<(u32, u32, char)> {
+        map.iter().map(|(block, &value)| (block.start().to_bits(), block.end().to_bits(), value)).collect()
+    }
+
+    #[test]
+    fn block_equals_addresses_within_bounds() {
+        let block = IpAddrBlock::new(Ipv4Addr::new(10, 0, 0, 0), Ipv4Addr::new(10, 0, 0, 255));
+
+        assert_eq!(block, Ipv4Addr::new(10, 0, 0, 0));
+        assert_eq!(block, Ipv4Addr::new(10, 0, 0, 128));
+        assert_eq!(block, Ipv4Addr::new(10, 0, 0, 255));
+        assert_ne!(block, Ipv4Addr::new(9, 255, 255, 255));
+        assert_ne!(block, Ipv4Addr::new(10, 0, 1, 0));
+
+        assert_eq!(Ipv4Addr::new(10, 0, 0, 0), block);
+        assert_ne!(Ipv4Addr::new(10, 0, 1, 0), block);
+    }
+
+    #[test]
+    fn block_equals_addresses_at_extremes() {
+        let first = IpAddrBlock::new(Ipv4Addr::UNSPECIFIED, Ipv4Addr::new(0, 0, 0, 255));
+        let last = IpAddrBlock::new(Ipv4Addr::new(255, 255, 255, 0), Ipv4Addr::BROADCAST);
+
+        assert_eq!(first, Ipv4Addr::UNSPECIFIED);
+        assert_eq!(first, Ipv4Addr::new(0, 0, 0, 255));
+        assert_ne!(first, Ipv4Addr::new(0, 0, 1, 0));
+        assert_eq!(last, Ipv4Addr::new(255, 255, 255, 0));
+        assert_eq!(last, Ipv4Addr::BROADCAST);
+        assert_ne!(last, Ipv4Addr::new(255, 255, 254, 255));
+
+        let all = IpAddrBlock::new(Ipv6Addr::UNSPECIFIED, Ipv6Addr::from_bits(u128::MAX));
+        let single = IpAddrBlock::new(Ipv6Addr::from_bits(1), Ipv6Addr::from_bits(1));
+
+        assert_eq!(all, Ipv6Addr::UNSPECIFIED);
+        assert_eq!(all, Ipv6Addr::from_bits(u128::MAX));
+        assert_eq!(single, Ipv6Addr::from_bits(1));
+        assert_ne!(single, Ipv6Addr::UNSPECIFIED);
+        assert_ne!(single, Ipv6Addr::from_bits(2));
+    }
+
+    #[test]
+    fn block_equality_agrees_with_ordering() {
+        let block = IpAddrBlock::new(Ipv4Addr::new(10, 0, 0, 0), Ipv4Addr::new(10, 0, 0, 255));
+
+        for bits in 0x09FF_FFF0 ..= 0x0A00_0110 {
+            let address = Ipv4Addr::from_bits(bits);
+
+            assert_eq!(block == address, block.partial_cmp(&address).is_some_and(core::cmp::Ordering::is_eq));
+            assert_eq!(block == address, block.range().contains(&address), "{address}");
+        }
+    }
+
+    #[test]
+    fn batch_lookup_matches_single_lookup() {
+        let overlapping = self::map(&[(0, 255, 'a'), (10, 20, 'b'), (15, 15, 'c'), (300, 400, 'd')]);
+        let disjoint = self::map(&[(0, 9, 'a'), (10, 20, 'b'), (300, 400, 'd')]);
+        let addresses: Vec<_> = [15, 0, 500, 21, 10, 350, 20, 255, 256].map(Ipv4Addr::from_bits).into();
+
+        for map in [overlapping, disjoint] {
+            let single: Vec<_> = addresses.iter().map(|address| map.get_from_address(*address)).collect();
+
+            assert_eq!(map.get_many_from_addresses(&addresses), single);
+        }
+    }
+
+    #[test]
+    fn union_of_disjoint_maps_keeps_every_entry() {
+        let mut left = self::map(&[(0, 9, 'a'), (20, 29, 'b')]);
+        let right = self::map(&[(10, 19, 'c'), (30, 39, 'd')]);
+
+        left.union_with(&right, |_, _| unreachable!("disjoint maps never conflict"));
+
+        assert_eq!(self::entries(&left), [(0, 9, 'a'), (10, 19, 'c'), (20, 29, 'b'), (30, 39, 'd')]);
+        assert!(left.is_disjoint());
+    }
+
+    #[test]
+    fn union_of_identical_maps_resolves_every_entry() {
+        let mut left = self::map(&[(0, 9, 'a'), (20, 29, 'b')]);
+        let right = left.clone();
+
+        left.union_with(&right, |l, r| if l == r { l.to_ascii_uppercase() } else { '?' });
+
+        assert_eq!(self::entries(&left), [(0, 9, 'A'), (20, 29, 'B')]);
+    }
+
+    #[test]
+    fn union_of_overlapping_maps_splits_conflicts() {
+        let mut left = self::map(&[(0, 19, 'a'), (30, 39, 'b')]);
+        let right = self::map(&[(10, 34, 'c'), (50, 59, 'd')]);
+
+        left.union_with(&right, |l, r| if (*l, *r) == ('a', 'c') { 'x' } else { 'y' });
+
+        assert_eq!(self::entries(&left), [
+            (0, 9, 'a'),
+            (10, 19, 'x'),
+            (20, 29, 'c'),
+            (30, 34, 'y'),
+            (35, 39, 'b'),
+            (50, 59, 'd')
+        ]);
+        assert!(left.is_disjoint());
+    }
+
+    #[test]
+    fn union_of_maps_with_overlapping_blocks_flattens_them() {
+        let mut left = self::map(&[(0, 255, 'a'), (10, 20, 'b')]);
+        let right = self::map(&[(100, 300, 'c')]);
+
+        left.union_with(&right, |l, r| if (*l, *r) == ('a', 'c') { 'x' } else { 'y' });
+
+        assert_eq!(self::entries(&left), [(0, 9, 'a'), (10, 20, 'b'), (21, 99, 'a'), (100, 255, 'x'), (256, 300, 'c')]);
+        assert_eq!(left.get_from_address(Ipv4Addr::from_bits(15)), Some(&'b'));
+        assert!(left.is_disjoint());
+
+        let mut left = self::map(&[(0, 9, 'a')]);
+        let right = self::map(&[(5, 50, 'b'), (20, 30, 'c')]);
+
+        left.union_with(&right, |_, r| r.to_ascii_uppercase());
+
+        assert_eq!(self::entries(&left), [(0, 4, 'a'), (5, 9, 'B'), (10, 19, 'b'), (20, 30, 'c'), (31, 50, 'b')]);
+        assert!(left.is_disjoint());
+    }
+
+    #[test]
+    fn union_with_empty_map_is_unchanged() {
+        let mut left = self::map(&[(0, 9, 'a')]);
+
+        left.union_with(&IpAddrBlockMap::new(), |_, _| unreachable!("an empty map never conflicts"));
+
+        assert_eq!(self::entries(&left), [(0, 9, 'a')]);
+
+        let mut empty = IpAddrBlockMap::new();
+
+        empty.union_with(&left, |_, _| unreachable!("an empty map never conflicts"));
+
+        assert_eq!(empty, left);
+    }
+
+    #[test]
+    fn merge_combines_touching_blocks() {
+        let mut blocks = alloc::vec![self::block(10, 19), self::block(0, 9), self::block(20, 29), self::block(31, 39)];
+
+        super::merge_blocks(&mut blocks);
+
+        assert_eq!(blocks, [self::block(0, 29), self::block(31, 39)]);
+
+        let mut blocks = alloc::vec![self::block(0, u32::MAX - 1), self::block(u32::MAX, u32::MAX)];
+
+        super::merge_blocks(&mut blocks);
+
+        assert_eq!(blocks, [self::block(0, u32::MAX)]);
+    }
+
+    #[test]
+    fn merge_combines_overlapping_blocks() {
+        let mut blocks = alloc::vec![self::block(0, 20), self::block(5, 10), self::block(15, 30), self::block(40, 50)];
+
+        super::merge_blocks(&mut blocks);
+
+        assert_eq!(blocks, [self::block(0, 30), self::block(40, 50)]);
+    }
+
+    #[test]
+    fn merge_keeps_separate_blocks() {
+        let mut blocks = alloc::vec![self::block(20, 29), self::block(0, 9), self::block(u32::MAX, u32::MAX)];
+
+        super::merge_blocks(&mut blocks);
+
+        assert_eq!(blocks, [self::block(0, 9), self::block(20, 29), self::block(u32::MAX, u32::MAX)]);
+    }
+}
@@ -0,0 +1,199 @@
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+
+use super::{Address, IpAddrBlock, IpAddrBlockMap};
+
+/// A CIDR prefix, consisting of a network address and a prefix length.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IpAddrPrefix<A: Address> {
+    network: A,
+    length: u8,
+}
+
+impl<A: Address> IpAddrPrefix<A> {
+    /// Creates a new [`IpAddrPrefix<A>`].
+    ///
+    /// Returns [`None`] if the length exceeds the address' width or if the network address has any host bits set.
+    #[must_use]
+    pub fn new(network: A, length: u8) -> Option<Self> {
+        let host_bits = A::BITS.checked_sub(u32::from(length))?;
+
+        (network.to_bits() & self::host_mask(host_bits) == 0).then_some(Self { network, length })
+    }
+
+    /// Returns the network address of this [`IpAddrPrefix<A>`].
+    #[inline]
+    pub const fn network(&self) -> A {
+        self.network
+    }
+
+    /// Returns the prefix length of this [`IpAddrPrefix<A>`].
+    #[inline]
+    pub const fn length(&self) -> u8 {
+        self.length
+    }
+
+    /// Returns the IP address block covered by this [`IpAddrPrefix<A>`].
+    #[must_use]
+    pub fn block(&self) -> IpAddrBlock<A> {
+        let bits = self.network.to_bits() | self::host_mask(A::BITS - u32::from(self.length));
+
+        IpAddrBlock(self.network, A::from_bits(bits))
+    }
+}
+
+impl<A: Address + Display> Display for IpAddrPrefix<A> {
+    #[inline]
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}/{}", self.network, self.length)
+    }
+}
+
+/// An error that is returned when trying to create an [`IpAddrBlock<A>`] from an invalid CIDR prefix.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum PrefixError<A: Address> {
+    /// The given prefix length exceeds the address' width.
+    TooLong {
+        /// The given prefix length.
+        length: u8,
+    },
+    /// The given network address has host bits set beyond the prefix length.
+    Unaligned {
+        /// The given network address.
+        network: A,
+        /// The given prefix length.
+        length: u8,
+    },
+}
+
+impl<A: Address + Debug + Display> core::error::Error for PrefixError<A> {}
+
+impl<A: Address + Display> Display for PrefixError<A> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooLong { length } => {
+                write!(f, "the prefix length {length} exceeds the address width of {}", A::BITS)
+            }
+            Self::Unaligned { network, length } => {
+                write!(f, "the network address {network} has host bits set beyond the prefix length {length}")
+            }
+        }
+    }
+}
+
+impl<A: Address> IpAddrBlock<A> {
+    /// Creates a new [`IpAddrBlock<A>`] covering the CIDR prefix with the given network address and prefix length.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the length exceeds the address' width or if the network address has any
+    /// host bits set.
+    pub fn from_prefix(network: A, length: u8) -> Result<Self, PrefixError<A>> {
+        if u32::from(length) > A::BITS {
+            return Err(PrefixError::TooLong { length });
+        }
+
+        IpAddrPrefix::new(network, length).map(|p| p.block()).ok_or(PrefixError::Unaligned { network, length })
+    }
+
+    /// Returns the number of addresses contained within this [`IpAddrBlock<A>`].
+    ///
+    /// Since a block spanning an entire 128-bit address space contains one more address than can be represented, the
+    /// returned value saturates at [`u128::MAX`].
+    #[must_use]
+    pub fn address_count(&self) -> u128 {
+        (self.end().to_bits() - self.start().to_bits()).saturating_add(1)
+    }
+
+    /// Returns the CIDR prefix that covers exactly this [`IpAddrBlock<A>`], if the block is a single aligned prefix.
+    #[must_use]
+    pub fn as_prefix(&self) -> Option<IpAddrPrefix<A>> {
+        let mut prefixes = self.prefixes();
+
+        prefixes.next().filter(|_| prefixes.next().is_none())
+    }
+
+    /// Returns an iterator over the minimal set of CIDR prefixes that cover exactly this [`IpAddrBlock<A>`].
+    pub fn prefixes(&self) -> impl Iterator<Item = IpAddrPrefix<A>> {
+        let end = self.end().to_bits();
+        let mut next = Some(self.start().to_bits());
+
+        core::iter::from_fn(move || {
+            let start = next?;
+            // The largest prefix is limited by both the start address' alignment and the remaining range.
+            let mut host_bits = start.trailing_zeros().min(A::BITS);
+
+            while host_bits > 0 && start | self::host_mask(host_bits) > end {
+                host_bits -= 1;
+            }
+
+            let last = start | self::host_mask(host_bits);
+
+            next = (last < end).then(|| last + 1);
+
+            // Address widths never exceed 128 bits, so this cannot truncate.
+            #[allow(clippy::cast_possible_truncation)]
+            let length = (A::BITS - host_bits) as u8;
+
+            Some(IpAddrPrefix { network: A::from_bits(start), length })
+        })
+    }
+}
+
+/// The result of resolving a CIDR prefix, rather than a single address, within an [`IpAddrBlockMap<A, T>`].
+///
+/// This is returned by [`get_from_prefix`](<IpAddrBlockMap::get_from_prefix>).
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum PrefixResolution<'m, T> {
+    /// No address within the prefix is mapped.
+    Unmapped,
+    /// Every address within the prefix is mapped to the same value.
+    Single(&'m T),
+    /// Some addresses within the prefix are mapped to the same value, and the rest are unmapped.
+    Partial(&'m T),
+    /// Addresses within the prefix are mapped to multiple values, which are listed in address order.
+    Ambiguous(Vec<&'m T>),
+}
+
+impl<A: Address, T: PartialEq> IpAddrBlockMap<A, T> {
+    /// Resolves the CIDR prefix with the given network address and prefix length, such as a client subnet that only
+    /// reveals the start of an address.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the length exceeds the address' width or if the network address has any
+    /// host bits set.
+    pub fn get_from_prefix(&self, network: A, length: u8) -> Result<PrefixResolution<'_, T>, PrefixError<A>> {
+        let block = IpAddrBlock::from_prefix(network, length)?;
+        let mut values = Vec::<&T>::new();
+        // The first address that has not yet been found within a block, or `None` once the entire prefix is covered.
+        let mut uncovered = Some(block.start().to_bits());
+        let mut gaps = false;
+
+        // Blocks are visited in order of their start addresses, so any gap is found before the blocks that follow it.
+        for (b, value) in self.overlapping(block) {
+            if let Some(next) = uncovered {
+                gaps |= b.start().to_bits() > next;
+                uncovered = (b.end() < block.end()).then(|| next.max(b.end().to_bits() + 1));
+            }
+
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+
+        gaps |= uncovered.is_some();
+
+        Ok(match (&*values, gaps) {
+            ([], _) => PrefixResolution::Unmapped,
+            ([value], false) => PrefixResolution::Single(value),
+            ([value], true) => PrefixResolution::Partial(value),
+            _ => PrefixResolution::Ambiguous(values),
+        })
+    }
+}
+
+/// Returns a mask with the given number of low bits set.
+const fn host_mask(host_bits: u32) -> u128 {
+    if host_bits >= u128::BITS { u128::MAX } else { (1 << host_bits) - 1 }
+}
@@ -1,10 +1,23 @@
-use std::net::Ipv4Addr;
+use core::net::Ipv4Addr;
 
 /// An IPv4 address block.
 pub type Ipv4AddrBlock = super::IpAddrBlock<Ipv4Addr>;
 /// An IPv4 address block map.
 pub type Ipv4AddrBlockMap<T> = super::IpAddrBlockMap<Ipv4Addr, T>;
+/// An IPv4 CIDR prefix.
+pub type Ipv4AddrPrefix = super::prefix::IpAddrPrefix<Ipv4Addr>;
 
-impl super::Address for Ipv4Addr {}
+impl super::Address for Ipv4Addr {
+    const BITS: u32 = 32;
 
-impl super::private::Sealed for Ipv4Addr {}
+    #[inline]
+    fn to_bits(self) -> u128 {
+        u128::from(Self::to_bits(self))
+    }
+
+    #[inline]
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_bits(bits: u128) -> Self {
+        Self::from_bits(bits as u32)
+    }
+}
@@ -1,10 +1,22 @@
-use std::net::Ipv6Addr;
+use core::net::Ipv6Addr;
 
 /// An IPv6 address block.
 pub type Ipv6AddrBlock = super::IpAddrBlock<Ipv6Addr>;
 /// An IPv6 address block map.
 pub type Ipv6AddrBlockMap<T> = super::IpAddrBlockMap<Ipv6Addr, T>;
+/// An IPv6 CIDR prefix.
+pub type Ipv6AddrPrefix = super::prefix::IpAddrPrefix<Ipv6Addr>;
 
-impl super::Address for Ipv6Addr {}
+impl super::Address for Ipv6Addr {
+    const BITS: u32 = 128;
 
-impl super::private::Sealed for Ipv6Addr {}
+    #[inline]
+    fn to_bits(self) -> u128 {
+        Self::to_bits(self)
+    }
+
+    #[inline]
+    fn from_bits(bits: u128) -> Self {
+        Self::from_bits(bits)
+    }
+}
@@ -4,7 +4,13 @@
 #![cfg_attr(debug_assertions, warn(clippy::unwrap_used))]
 #![warn(clippy::nursery, clippy::pedantic, clippy::todo)]
 #![allow(clippy::module_name_repetitions)]
+#![no_std]
 
+extern crate alloc;
+
+/// Defines the autonomous system number block API.
+#[cfg(feature = "asn")]
+pub mod asn;
 /// Defines countries and their API.
 pub mod country;
 /// Defines the IPv4 and IPv6 block API.
@@ -12,7 +18,9 @@ pub mod ip;
 
 /// The library's default import prelude.
 pub mod prelude {
-    pub use crate::country::{Country, CountryCode};
-    pub use crate::ip::v4::{Ipv4AddrBlock, Ipv4AddrBlockMap};
-    pub use crate::ip::v6::{Ipv6AddrBlock, Ipv6AddrBlockMap};
+    #[cfg(feature = "asn")]
+    pub use crate::asn::{Asn, AsnBlock, AsnBlockMap};
+    pub use crate::country::{Country, CountryCode, NumericCode};
+    pub use crate::ip::v4::{Ipv4AddrBlock, Ipv4AddrBlockMap, Ipv4AddrPrefix};
+    pub use crate::ip::v6::{Ipv6AddrBlock, Ipv6AddrBlockMap, Ipv6AddrPrefix};
 }
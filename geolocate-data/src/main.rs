@@ -5,11 +5,11 @@
 #![warn(clippy::nursery, clippy::pedantic, clippy::todo)]
 #![allow(clippy::module_name_repetitions)]
 
-use std::num::ParseIntError;
 use std::path::Path;
+use std::time::Duration;
 
-use clap::Parser;
-use geolocate_core::country::InvalidCodeError;
+use clap::{Parser, ValueEnum};
+use geolocate_core::country::{Country, CountryError, InvalidCodeError, InvalidNumericError};
 
 /// Provides the application's mediawiki API.
 pub mod wiki;
@@ -23,12 +23,21 @@ pub enum Error {
     /// An error from an invalid country code.
     #[error(transparent)]
     InvalidCode(#[from] InvalidCodeError),
+    /// An error during writing CSV.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
     /// An error from an IO operation.
     #[error(transparent)]
     Io(#[from] std::io::Error),
+    /// An error from an invalid numeric country code.
+    #[error(transparent)]
+    InvalidNumeric(#[from] InvalidNumericError),
+    /// An error from an invalid country.
+    #[error(transparent)]
+    InvalidCountry(#[from] CountryError),
     /// An error from parsing an integer.
     #[error(transparent)]
-    ParseInt(#[from] ParseIntError),
+    ParseInt(#[from] std::num::ParseIntError),
     /// An error during serializing or deserializing JSON.
     #[error(transparent)]
     Json(#[from] serde_json::Error),
@@ -43,6 +52,30 @@ pub enum Error {
 pub struct Arguments {
     /// The file to output country data into.
     pub path: Box<Path>,
+    /// The format to output country data in.
+    #[arg(short = 'f', long = "format", value_enum, default_value = "json")]
+    pub format: Format,
+    /// A file to cache the raw Wikidata response within, which is reused instead of querying while it is fresh.
+    #[arg(short = 'c', long = "cache")]
+    pub cache: Option<Box<Path>>,
+    /// The number of seconds that a cached Wikidata response remains fresh for.
+    #[arg(short = 't', long = "cache-lifetime", default_value = "86400", requires = "cache")]
+    pub cache_lifetime: u64,
+    /// The language to name countries in. If given multiple times, names in each following language are also stored.
+    #[arg(short = 'l', long = "language", default_value = "en", value_parser = crate::parse_language)]
+    pub languages: Vec<Box<str>>,
+}
+
+/// The formats that country data may be output in.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A pretty-printed JSON array.
+    #[default]
+    Json,
+    /// CSV rows of each country's name, alpha-2 code, and numeric code, preceded by a header row.
+    ///
+    /// Unknown numeric codes are written as empty fields, and names in additional languages and timezones are omitted.
+    Csv,
 }
 
 /// The application's entrypoint.
@@ -51,13 +84,16 @@ pub struct Arguments {
 ///
 /// This function will return an error if the program fails to run.
 pub fn main() -> Result<()> {
-    let Arguments { path } = Arguments::parse();
+    let Arguments { path, format, cache, cache_lifetime, languages } = Arguments::parse();
 
-    let mut countries = crate::wiki::wiki_data()?;
+    let mut countries = crate::wiki::wiki_data(cache.as_deref(), Duration::from_secs(cache_lifetime), &languages)?;
 
     countries.sort_unstable_by_key(|c| c.numeric);
 
-    let contents = serde_json::to_vec_pretty(&countries)?;
+    let contents = match format {
+        Format::Json => serde_json::to_vec_pretty(&countries)?,
+        Format::Csv => self::to_csv(&countries)?,
+    };
 
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)?;
@@ -69,3 +105,35 @@ pub fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Parses a Wikidata language code, such as `en` or `pt-br`.
+///
+/// # Errors
+///
+/// This function will return an error if the code is empty or contains characters other than letters and hyphens.
+pub fn parse_language(value: &str) -> Result<Box<str>, String> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_alphabetic() || b == b'-') {
+        return Err(format!("invalid language code '{value}'"));
+    }
+
+    Ok(value.to_ascii_lowercase().into_boxed_str())
+}
+
+/// Serializes the given countries into CSV rows, preceded by a header row.
+///
+/// # Errors
+///
+/// This function will return an error if the countries could not be serialized.
+pub fn to_csv(countries: &[Country]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(["name", "alpha2", "numeric"])?;
+
+    for Country { name, code, numeric, .. } in countries {
+        let numeric = numeric.map(|numeric| numeric.to_string()).unwrap_or_default();
+
+        writer.write_record([&**name, &code.to_string(), &numeric])?;
+    }
+
+    writer.into_inner().map_err(|error| error.into_error().into())
+}
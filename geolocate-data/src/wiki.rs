@@ -1,8 +1,12 @@
+use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 use geolocate_core::country::{Country, CountryCode};
 use mediawiki::ApiSync;
 use serde::Deserialize;
+use serde_json::Value;
 
 use crate::Result;
 
@@ -37,8 +41,12 @@ pub struct ResponseBinding {
     pub name: ResponseBindingEntry,
     /// The country's alpha-2 code.
     pub code: ResponseBindingEntry,
-    /// The country's numeric identifier.
-    pub numeric: ResponseBindingEntry,
+    /// The country's alpha-3 code, if it is known.
+    pub alpha3: Option<ResponseBindingEntry>,
+    /// The country's numeric identifier, if it is known.
+    pub numeric: Option<ResponseBindingEntry>,
+    /// The country's space-separated IANA timezone identifiers, if any are known.
+    pub timezones: Option<ResponseBindingEntry>,
 }
 
 /// A value within a response binding.
@@ -53,19 +61,64 @@ pub struct ResponseBindingEntry {
 
 /// Queries Wikidata, returning a list of known ISO-3166 countries.
 ///
+/// Country names are given in the first of the given languages, and each following language's names are stored
+/// within [`Country::names`].
+///
+/// If a cache path is given and the file there is younger than the given lifetime, the raw responses are loaded from it
+/// instead of querying Wikidata. Otherwise, the raw responses are written to it after querying.
+///
 /// # Errors
 ///
-/// This function will return an error if .
-pub fn wiki_data() -> Result<Box<[Country]>> {
-    let client = ApiSync::new("https://www.wikidata.org/w/api.php")?;
-    let output = client.sparql_query(&wiki_query(0))?;
+/// This function will return an error if the query fails, the cache cannot be accessed, or the response is invalid.
+pub fn wiki_data(cache: Option<&Path>, cache_lifetime: Duration, languages: &[Box<str>]) -> Result<Box<[Country]>> {
+    let Some((primary, languages)) = languages.split_first() else {
+        return Ok(Box::default());
+    };
+
+    let mut countries = self::wiki_countries(cache, cache_lifetime, primary)?;
+
+    for language in languages {
+        let names: HashMap<_, _> = self::wiki_countries(cache, cache_lifetime, language)?
+            .into_vec()
+            .into_iter()
+            .map(|c| (c.code, c.name))
+            .collect();
+
+        for country in &mut countries {
+            // Wikidata falls back to the entity's identifier if it has no label in the requested language.
+            if let Some(name) = names.get(&country.code).filter(|name| !self::is_entity_id(name)) {
+                country.names.insert(language.clone(), name.clone());
+            }
+        }
+    }
+
+    Ok(countries)
+}
+
+/// Queries Wikidata, returning a list of known ISO-3166 countries with names in the given language.
+///
+/// # Errors
+///
+/// This function will return an error if the query fails, the cache cannot be accessed, or the response is invalid.
+fn wiki_countries(cache: Option<&Path>, cache_lifetime: Duration, language: &str) -> Result<Box<[Country]>> {
+    let output = match cache {
+        Some(path) => self::cached_wiki_response(path, cache_lifetime, language)?,
+        None => self::wiki_response(language)?,
+    };
     let response = serde_json::from_value::<Response>(output)?;
 
     let mut countries = Vec::with_capacity(response.results.bindings.len());
 
-    for ResponseBinding { name, code, numeric } in response.results.bindings {
+    for ResponseBinding { name, code, alpha3, numeric, timezones } in response.results.bindings {
         let code = CountryCode::from_str(&code.value)?;
-        let country = Country::new(name.value, code, numeric.value.parse()?);
+        let numeric = numeric.map(|numeric| u16::from_str(&numeric.value)).transpose()?;
+        let mut country = Country::try_new(name.value, code, numeric)?;
+
+        country.alpha3 = alpha3.map(|alpha3| CountryCode::from_str(&alpha3.value)).transpose()?;
+
+        if let Some(timezones) = timezones {
+            country.timezones = timezones.value.split_whitespace().map(Box::from).collect();
+        }
 
         countries.push(country);
     }
@@ -73,26 +126,203 @@ pub fn wiki_data() -> Result<Box<[Country]>> {
     Ok(countries.into_boxed_slice())
 }
 
-/// Creates a new query with the given entry limit.
+/// Queries Wikidata for country names in the given language, returning its raw response.
+///
+/// # Errors
+///
+/// This function will return an error if the query fails.
+pub fn wiki_response(language: &str) -> Result<Value> {
+    let client = ApiSync::new("https://www.wikidata.org/w/api.php")?;
+
+    Ok(client.sparql_query(&wiki_query(0, language))?)
+}
+
+/// Loads Wikidata's raw response for the given language from the given cache file, querying and caching it if it is
+/// missing or expired.
+///
+/// The cache file contains an object of each language's raw response, keyed by its language code.
+///
+/// # Errors
+///
+/// This function will return an error if the query fails or the cache cannot be accessed.
+pub fn cached_wiki_response(path: &Path, lifetime: Duration, language: &str) -> Result<Value> {
+    let is_fresh = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age < lifetime));
+
+    // Expired or unrecognized caches are discarded entirely, so that every response shares the same age.
+    let mut responses = if is_fresh {
+        serde_json::from_slice::<HashMap<Box<str>, Value>>(&std::fs::read(path)?).unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    if let Some(output) = responses.get(language) {
+        return Ok(output.clone());
+    }
+
+    let output = self::wiki_response(language)?;
+
+    responses.insert(language.into(), output.clone());
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, serde_json::to_vec(&responses)?)?;
+
+    Ok(output)
+}
+
+/// Creates a new query with the given entry limit that labels countries in the given language.
+///
+/// This is the query built by a default [`QueryBuilder`].
 #[must_use]
-pub fn wiki_query(limit: usize) -> String {
-    const QUERY: &str = r#"
-SELECT
-    ?nameLabel
-    ?code
-    ?numeric
-WHERE
-{
-    ?name wdt:P31 wd:Q6256;
-        wdt:P297 ?code;
-        wdt:P299 ?numeric.
-    SERVICE wikibase:label
+pub fn wiki_query(limit: usize, language: &str) -> String {
+    QueryBuilder::default().limit(limit).language(language).build()
+}
+
+/// Builds a Wikidata SPARQL query for country data.
+///
+/// Every queried entity must have an alpha-2 code, and its label is always fetched as its name. The default builder
+/// queries sovereign countries (`Q6256`) with every optional property, labelled in English, without a limit.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct QueryBuilder {
+    /// The identifiers of the classes that an entity must be an instance of, such as `Q6256`.
+    classes: Vec<Box<str>>,
+    /// Whether to fetch each country's alpha-3 code.
+    alpha3: bool,
+    /// Whether to fetch each country's numeric code.
+    numeric: bool,
+    /// Whether to fetch each country's timezones.
+    timezones: bool,
+    /// The maximum number of entries to return, or zero for no limit.
+    limit: usize,
+    /// The language to label countries in.
+    language: Box<str>,
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        Self {
+            classes: vec!["Q6256".into()],
+            alpha3: true,
+            numeric: true,
+            timezones: true,
+            limit: 0,
+            language: "en".into(),
+        }
+    }
+}
+
+impl QueryBuilder {
+    /// Sets the classes that an entity may be an instance of, such as `Q3024240` for historical countries.
+    ///
+    /// An entity is queried if it is an instance of any of the given classes.
+    #[must_use]
+    pub fn classes<I, S>(mut self, classes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<Box<str>>,
     {
-        bd:serviceParam wikibase:language "en".
+        self.classes = classes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets whether to fetch each country's alpha-3 code (`P298`).
+    #[inline]
+    #[must_use]
+    pub const fn alpha3(mut self, alpha3: bool) -> Self {
+        self.alpha3 = alpha3;
+        self
+    }
+
+    /// Sets whether to fetch each country's numeric code (`P299`).
+    #[inline]
+    #[must_use]
+    pub const fn numeric(mut self, numeric: bool) -> Self {
+        self.numeric = numeric;
+        self
+    }
+
+    /// Sets whether to fetch each country's timezones.
+    ///
+    /// These are found through the IANA identifiers (`P6687`) of the timezones that a country is located within
+    /// (`P421`), and are grouped into a single space-separated value.
+    #[inline]
+    #[must_use]
+    pub const fn timezones(mut self, timezones: bool) -> Self {
+        self.timezones = timezones;
+        self
+    }
+
+    /// Sets the maximum number of entries to return, where zero means that there is no limit.
+    #[inline]
+    #[must_use]
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Sets the language to label countries in.
+    #[must_use]
+    pub fn language(mut self, language: impl Into<Box<str>>) -> Self {
+        self.language = language.into();
+        self
     }
-}"#;
 
-    let query = QUERY.trim().replace("    ", "").replace('\n', " ");
+    /// Returns the built SPARQL query.
+    #[must_use]
+    pub fn build(&self) -> String {
+        let mut variables = vec!["?code"];
+
+        if self.alpha3 {
+            variables.push("?alpha3");
+        }
+        if self.numeric {
+            variables.push("?numeric");
+        }
+
+        let variables = variables.join(" ");
+        let mut clauses = vec![format!("SELECT ?nameLabel {variables}")];
+
+        if self.timezones {
+            clauses.push(r#"(GROUP_CONCAT(DISTINCT ?timezone; separator=" ") AS ?timezones)"#.into());
+        }
+
+        clauses.push("WHERE {".into());
+
+        // A single class is matched directly, rather than through a `VALUES` clause.
+        if let [class] = &*self.classes {
+            clauses.push(format!("?name wdt:P31 wd:{class}; wdt:P297 ?code."));
+        } else {
+            let values = self.classes.iter().map(|class| format!("wd:{class}")).collect::<Vec<_>>().join(" ");
+
+            clauses.push(format!("VALUES ?class {{ {values} }}"));
+            clauses.push("?name wdt:P31 ?class; wdt:P297 ?code.".into());
+        }
+
+        if self.alpha3 {
+            clauses.push("OPTIONAL { ?name wdt:P298 ?alpha3. }".into());
+        }
+        if self.numeric {
+            clauses.push("OPTIONAL { ?name wdt:P299 ?numeric. }".into());
+        }
+        if self.timezones {
+            clauses.push("OPTIONAL { ?name wdt:P421 ?zone. ?zone wdt:P6687 ?timezone. }".into());
+        }
+
+        clauses.push(format!(r#"SERVICE wikibase:label {{ bd:serviceParam wikibase:language "{}". }}"#, self.language));
+        clauses.push("}".into());
+        clauses.push(format!("GROUP BY ?nameLabel {variables}"));
+
+        let query = clauses.join(" ");
+
+        if self.limit > 0 { format!("{query}\nLIMIT {}", self.limit) } else { query }
+    }
+}
 
-    if limit > 0 { format!("{query}\nLIMIT {limit}") } else { query }
+/// Returns whether the given label is a Wikidata entity identifier, such as `Q183`.
+fn is_entity_id(label: &str) -> bool {
+    label.strip_prefix('Q').is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
 }